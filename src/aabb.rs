@@ -1,5 +1,6 @@
 use crate::interval::Interval;
 use crate::ray::Ray;
+use crate::stats::{self, Counter};
 use crate::vec3::Vec3;
 use std::ops::Add;
 
@@ -86,7 +87,23 @@ impl Aabb {
         }
     }
 
+    pub fn centroid(&self) -> Vec3 {
+        Vec3([
+            (self.x.min + self.x.max) / 2.0,
+            (self.y.min + self.y.max) / 2.0,
+            (self.z.min + self.z.max) / 2.0,
+        ])
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let (x, y, z) = (self.x.size(), self.y.size(), self.z.size());
+        2.0 * (x * y + y * z + z * x)
+    }
+
     pub fn hit(&self, ray: &Ray, ray_t: Interval) -> bool {
+        stats::count(Counter::AabbTests);
+        let mut min = ray_t.min;
+        let mut max = ray_t.max;
         for axis in 0..3 {
             let interval = self.axis_interval(axis);
             let adinv = 1.0 / ray.direction[axis as usize];
@@ -94,8 +111,6 @@ impl Aabb {
             let t0 = (interval.min - ray.origin[axis as usize]) * adinv;
             let t1 = (interval.max - ray.origin[axis as usize]) * adinv;
 
-            let mut min = ray_t.min;
-            let mut max = ray_t.max;
             if t0 < t1 {
                 if t0 > min {
                     min = t0
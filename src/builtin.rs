@@ -0,0 +1,783 @@
+use crate::camera::{Camera, CameraBuilder};
+use crate::sampler::SamplerKind;
+use crate::scene::{MaterialDescription, ObjectDescription, Scene, TextureDescription};
+use crate::vec3::Vec3;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::path::PathBuf;
+
+/// Seeds the randomly scattered objects of `bouncing` and `fancy`, so those scenes are the same
+/// on every run and their renders can be compared and resumed.
+const LAYOUT_SEED: u64 = 42;
+
+/// Looks up one of the scenes that ship with the renderer by name.
+pub fn scene(name: &str) -> Option<Scene> {
+    Some(match name {
+        "triplet" => triplet(),
+        "bouncing" => bouncing_final(),
+        "redblue" => redblue(),
+        "checkered" => checkered(),
+        "earth" => earth(),
+        "perlin" => perlin(),
+        "quads" => quads(),
+        "simple-light" => simple_light(),
+        "cornell" => cornell_box(),
+        "cornell-smoke" => cornell_smoke(),
+        "fancy-full" => fancy(800, 10000, 40),
+        "fancy-light" => fancy(400, 250, 4),
+        _ => return None,
+    })
+}
+
+fn solid(color: Vec3) -> TextureDescription {
+    TextureDescription::Solid { color }
+}
+
+fn lambertian(color: Vec3) -> MaterialDescription {
+    MaterialDescription::Lambertian {
+        texture: solid(color),
+    }
+}
+
+fn sphere(center: Vec3, radius: f64, material: &str) -> ObjectDescription {
+    ObjectDescription::Sphere {
+        center,
+        radius,
+        material: material.to_string(),
+    }
+}
+
+fn quad(q: Vec3, u: Vec3, v: Vec3, material: &str) -> ObjectDescription {
+    ObjectDescription::Quad {
+        q,
+        u,
+        v,
+        material: material.to_string(),
+    }
+}
+
+fn make_box(a: Vec3, b: Vec3, material: &str) -> ObjectDescription {
+    ObjectDescription::Box {
+        a,
+        b,
+        material: material.to_string(),
+    }
+}
+
+fn translate(object: ObjectDescription, offset: Vec3) -> ObjectDescription {
+    ObjectDescription::Translate {
+        offset,
+        object: Box::new(object),
+    }
+}
+
+fn rotate_y(object: ObjectDescription, angle: f64) -> ObjectDescription {
+    ObjectDescription::RotateY {
+        angle,
+        object: Box::new(object),
+    }
+}
+
+fn constant_medium(boundary: ObjectDescription, density: f64, material: &str) -> ObjectDescription {
+    ObjectDescription::ConstantMedium {
+        density,
+        material: material.to_string(),
+        boundary: Box::new(boundary),
+    }
+}
+
+fn materials<const N: usize>(
+    list: [(&str, MaterialDescription); N],
+) -> BTreeMap<String, MaterialDescription> {
+    list.into_iter()
+        .map(|(name, material)| (name.to_string(), material))
+        .collect()
+}
+
+fn triplet() -> Scene {
+    let materials = materials([
+        ("ground", lambertian(Vec3([0.8, 0.8, 0.0]))),
+        ("center", lambertian(Vec3([0.1, 0.2, 0.5]))),
+        (
+            "left",
+            MaterialDescription::Dielectric {
+                refraction_index: 1.5,
+            },
+        ),
+        (
+            "bubble",
+            MaterialDescription::Dielectric {
+                refraction_index: 1.0 / 1.5,
+            },
+        ),
+        (
+            "right",
+            MaterialDescription::Metal {
+                albedo: Vec3([0.8, 0.6, 0.2]),
+                fuzz: 1.0,
+            },
+        ),
+    ]);
+
+    let objects = vec![
+        sphere(Vec3([0.0, -100.5, -1.0]), 100.0, "ground"),
+        sphere(Vec3([0.0, 0.0, -1.2]), 0.5, "center"),
+        sphere(Vec3([-1.0, 0.0, -1.0]), 0.5, "left"),
+        sphere(Vec3([-1.0, 0.0, -1.0]), 0.4, "bubble"),
+        sphere(Vec3([1.0, 0.0, -1.0]), 0.5, "right"),
+    ];
+
+    let camera = Camera::builder()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(90.0)
+        .look_from(Vec3([-2.0, 2.0, 1.0]))
+        .look_at(Vec3([0.0, 0.0, -1.0]))
+        .defocus_angle(10.0)
+        .focus_dist(3.4)
+        .background(Vec3([0.7, 0.8, 1.0]));
+
+    Scene {
+        camera,
+        materials,
+        objects,
+        ..Default::default()
+    }
+}
+
+fn redblue() -> Scene {
+    let r = (PI / 4.0).cos();
+    let materials = materials([
+        ("left", lambertian(Vec3::z(1.0))),
+        ("right", lambertian(Vec3::x(1.0))),
+    ]);
+
+    let objects = vec![
+        sphere(Vec3([-r, 0.0, -1.0]), r, "left"),
+        sphere(Vec3([r, 0.0, -1.0]), r, "right"),
+    ];
+
+    let camera = Camera::builder()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(90.0)
+        .look_from(Vec3([0.0, 0.0, 1.0]))
+        .look_at(Vec3([0.0, 0.0, 0.0]))
+        .background(Vec3([0.7, 0.8, 1.0]));
+
+    Scene {
+        camera,
+        materials,
+        objects,
+        ..Default::default()
+    }
+}
+
+fn checker_ground() -> MaterialDescription {
+    MaterialDescription::Lambertian {
+        texture: TextureDescription::Checker {
+            scale: 0.32,
+            even: Box::new(solid(Vec3([0.2, 0.3, 0.1]))),
+            odd: Box::new(solid(Vec3([0.9, 0.9, 0.9]))),
+        },
+    }
+}
+
+fn bouncing_final() -> Scene {
+    let mut materials = materials([
+        ("ground", checker_ground()),
+        (
+            "glass",
+            MaterialDescription::Dielectric {
+                refraction_index: 1.5,
+            },
+        ),
+        ("brown", lambertian(Vec3([0.4, 0.2, 0.1]))),
+        (
+            "mirror",
+            MaterialDescription::Metal {
+                albedo: Vec3([0.7, 0.6, 0.5]),
+                fuzz: 0.0,
+            },
+        ),
+    ]);
+
+    let mut objects = vec![sphere(Vec3([0.0, -1000.0, 0.0]), 1000.0, "ground")];
+
+    let mut rand = SmallRng::seed_from_u64(LAYOUT_SEED);
+    for a in -11..11 {
+        for b in -11..11 {
+            let mat = rand.gen::<f64>();
+            let center = Vec3([
+                a as f64 + 0.9 * rand.gen::<f64>(),
+                0.2,
+                b as f64 + 0.9 * rand.gen::<f64>(),
+            ]);
+
+            if (center - Vec3([4.0, 0.2, 0.0])).length() > 0.9 {
+                let name = format!("sphere-{a}-{b}");
+                if mat < 0.9 {
+                    materials.insert(
+                        name.clone(),
+                        lambertian(Vec3::random(&mut rand) * Vec3::random(&mut rand)),
+                    );
+                    let end = center + Vec3([0.0, rand.gen(), 0.0]);
+                    objects.push(ObjectDescription::MovingSphere {
+                        start: center,
+                        end,
+                        radius: 0.2,
+                        material: name,
+                    });
+                } else if mat < 0.95 {
+                    materials.insert(
+                        name.clone(),
+                        MaterialDescription::Metal {
+                            albedo: Vec3::random_within(0.5, 1.0, &mut rand),
+                            fuzz: rand.gen::<f64>(),
+                        },
+                    );
+                    objects.push(sphere(center, 0.2, &name));
+                } else {
+                    objects.push(sphere(center, 0.2, "glass"));
+                }
+            }
+        }
+    }
+
+    objects.push(sphere(Vec3([0.0, 1.0, 0.0]), 1.0, "glass"));
+    objects.push(sphere(Vec3([-4.0, 1.0, 0.0]), 1.0, "brown"));
+    objects.push(sphere(Vec3([4.0, 1.0, 0.0]), 1.0, "mirror"));
+
+    let camera = Camera::builder()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(20.0)
+        .look_from(Vec3([13.0, 2.0, 3.0]))
+        .look_at(Vec3([0.0, 0.0, 0.0]))
+        .defocus_angle(0.6)
+        .background(Vec3([0.7, 0.8, 1.0]));
+
+    Scene {
+        camera,
+        materials,
+        objects,
+        ..Default::default()
+    }
+}
+
+fn checkered() -> Scene {
+    let materials = materials([("ground", checker_ground())]);
+
+    let objects = vec![
+        sphere(Vec3([0.0, -10.0, 0.0]), 10.0, "ground"),
+        sphere(Vec3([0.0, 10.0, 0.0]), 10.0, "ground"),
+    ];
+
+    let camera = Camera::builder()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(20.0)
+        .look_from(Vec3([13.0, 2.0, 3.0]))
+        .look_at(Vec3([0.0, 0.0, 0.0]))
+        .background(Vec3([0.7, 0.8, 1.0]));
+
+    Scene {
+        camera,
+        materials,
+        objects,
+        ..Default::default()
+    }
+}
+
+fn earth() -> Scene {
+    let materials = materials([(
+        "earth",
+        MaterialDescription::Lambertian {
+            texture: TextureDescription::Image {
+                path: PathBuf::from("earthmap.jpg"),
+            },
+        },
+    )]);
+
+    let objects = vec![sphere(Vec3::scalar(0.0), 2.0, "earth")];
+
+    let camera = Camera::builder()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(20.0)
+        .look_from(Vec3([0.0, 0.0, 12.0]))
+        .look_at(Vec3([0.0, 0.0, 0.0]))
+        .background(Vec3([0.7, 0.8, 1.0]));
+
+    Scene {
+        camera,
+        materials,
+        objects,
+        ..Default::default()
+    }
+}
+
+fn perlin() -> Scene {
+    let materials = materials([(
+        "ground",
+        MaterialDescription::Lambertian {
+            texture: TextureDescription::Noise {
+                scale: 4.0,
+                seed: None,
+            },
+        },
+    )]);
+
+    let objects = vec![
+        sphere(Vec3([0.0, -1000.0, 0.0]), 1000.0, "ground"),
+        sphere(Vec3([0.0, 2.0, 0.0]), 2.0, "ground"),
+    ];
+
+    let camera = Camera::builder()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(20.0)
+        .look_from(Vec3([13.0, 2.0, 3.0]))
+        .look_at(Vec3([0.0, 0.0, 0.0]))
+        .background(Vec3([0.7, 0.8, 1.0]));
+
+    Scene {
+        camera,
+        materials,
+        objects,
+        ..Default::default()
+    }
+}
+
+fn quads() -> Scene {
+    let materials = materials([
+        ("left-red", lambertian(Vec3([1.0, 0.2, 0.2]))),
+        ("back-green", lambertian(Vec3([0.2, 1.0, 0.2]))),
+        ("right-blue", lambertian(Vec3([0.2, 0.2, 1.0]))),
+        ("upper-orange", lambertian(Vec3([1.0, 0.5, 0.0]))),
+        ("lower-teal", lambertian(Vec3([0.2, 0.8, 0.8]))),
+    ]);
+
+    let objects = vec![
+        quad(
+            Vec3([-3.0, -2.0, 5.0]),
+            Vec3([0.0, 0.0, -4.0]),
+            Vec3([0.0, 4.0, 0.0]),
+            "left-red",
+        ),
+        quad(
+            Vec3([-2.0, -2.0, 0.0]),
+            Vec3([4.0, 0.0, 0.0]),
+            Vec3([0.0, 4.0, 0.0]),
+            "back-green",
+        ),
+        quad(
+            Vec3([3.0, -2.0, 1.0]),
+            Vec3([0.0, 0.0, 4.0]),
+            Vec3([0.0, 4.0, 0.0]),
+            "right-blue",
+        ),
+        quad(
+            Vec3([-2.0, 3.0, 1.0]),
+            Vec3([4.0, 0.0, 0.0]),
+            Vec3([0.0, 0.0, 4.0]),
+            "upper-orange",
+        ),
+        quad(
+            Vec3([-2.0, -3.0, 5.0]),
+            Vec3([4.0, 0.0, 0.0]),
+            Vec3([0.0, 0.0, -4.0]),
+            "lower-teal",
+        ),
+    ];
+
+    let camera = Camera::builder()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(80.0)
+        .look_from(Vec3([0.0, 0.0, 9.0]))
+        .look_at(Vec3([0.0, 0.0, 0.0]))
+        .background(Vec3([0.7, 0.8, 1.0]));
+
+    Scene {
+        camera,
+        materials,
+        objects,
+        ..Default::default()
+    }
+}
+
+fn simple_light() -> Scene {
+    let materials = materials([
+        (
+            "ground",
+            MaterialDescription::Lambertian {
+                texture: TextureDescription::Noise {
+                    scale: 4.0,
+                    seed: None,
+                },
+            },
+        ),
+        (
+            "light",
+            MaterialDescription::DiffuseLight {
+                texture: solid(Vec3([4.0, 4.0, 4.0])),
+            },
+        ),
+    ]);
+
+    let objects = vec![
+        sphere(Vec3([0.0, -1000.0, 0.0]), 1000.0, "ground"),
+        sphere(Vec3([0.0, 2.0, 0.0]), 2.0, "ground"),
+        sphere(Vec3([0.0, 7.0, 0.0]), 2.0, "light"),
+        quad(
+            Vec3([3.0, 1.0, -2.0]),
+            Vec3([2.0, 0.0, 0.0]),
+            Vec3([0.0, 2.0, 0.0]),
+            "light",
+        ),
+    ];
+
+    let camera = Camera::builder()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(20.0)
+        .look_from(Vec3([26.0, 3.0, 6.0]))
+        .look_at(Vec3([0.0, 2.0, 0.0]))
+        .background(Vec3([0.0, 0.0, 0.0]));
+
+    Scene {
+        camera,
+        materials,
+        objects,
+        ..Default::default()
+    }
+}
+
+fn cornell_materials(light: f64) -> BTreeMap<String, MaterialDescription> {
+    materials([
+        ("green", lambertian(Vec3([0.12, 0.45, 0.15]))),
+        ("red", lambertian(Vec3([0.65, 0.05, 0.05]))),
+        (
+            "light",
+            MaterialDescription::DiffuseLight {
+                texture: solid(Vec3::scalar(light)),
+            },
+        ),
+        ("white", lambertian(Vec3([0.73, 0.73, 0.73]))),
+    ])
+}
+
+fn cornell_camera() -> CameraBuilder {
+    Camera::builder()
+        .aspect_ratio(1.0)
+        .image_width(600)
+        .samples_per_pixel(200)
+        .max_depth(50)
+        .vfov(40.0)
+        .look_from(Vec3([278.0, 278.0, -800.0]))
+        .look_at(Vec3([278.0, 278.0, 0.0]))
+        .background(Vec3([0.0, 0.0, 0.0]))
+}
+
+fn cornell_box() -> Scene {
+    let objects = vec![
+        quad(
+            Vec3([555.0, 0.0, 0.0]),
+            Vec3([0.0, 555.0, 0.0]),
+            Vec3([0.0, 0.0, 555.0]),
+            "green",
+        ),
+        quad(
+            Vec3([0.0, 0.0, 0.0]),
+            Vec3([0.0, 555.0, 0.0]),
+            Vec3([0.0, 0.0, 555.0]),
+            "red",
+        ),
+        quad(
+            Vec3([343.0, 554.0, 332.0]),
+            Vec3([-130.0, 0.0, 0.0]),
+            Vec3([0.0, 0.0, -105.0]),
+            "light",
+        ),
+        quad(
+            Vec3([0.0, 0.0, 0.0]),
+            Vec3([555.0, 0.0, 0.0]),
+            Vec3([0.0, 0.0, 555.0]),
+            "white",
+        ),
+        quad(
+            Vec3([555.0, 555.0, 555.0]),
+            Vec3([-555.0, 0.0, 0.0]),
+            Vec3([0.0, 0.0, -555.0]),
+            "white",
+        ),
+        quad(
+            Vec3([0.0, 0.0, 555.0]),
+            Vec3([555.0, 0.0, 0.0]),
+            Vec3([0.0, 555.0, 0.0]),
+            "white",
+        ),
+        translate(
+            rotate_y(
+                make_box(Vec3([0.0, 0.0, 0.0]), Vec3([165.0, 330.0, 165.0]), "white"),
+                15.0,
+            ),
+            Vec3([265.0, 0.0, 295.0]),
+        ),
+        translate(
+            rotate_y(
+                make_box(Vec3([0.0, 0.0, 0.0]), Vec3([165.0, 165.0, 165.0]), "white"),
+                -18.0,
+            ),
+            Vec3([130.0, 0.0, 65.0]),
+        ),
+    ];
+
+    Scene {
+        camera: cornell_camera(),
+        materials: cornell_materials(15.0),
+        objects,
+        ..Default::default()
+    }
+}
+
+fn cornell_smoke() -> Scene {
+    let mut materials = cornell_materials(7.0);
+    materials.insert(
+        "smoke".to_string(),
+        MaterialDescription::Isotropic {
+            texture: solid(Vec3::scalar(0.0)),
+        },
+    );
+    materials.insert(
+        "fog".to_string(),
+        MaterialDescription::Isotropic {
+            texture: solid(Vec3::scalar(1.0)),
+        },
+    );
+
+    let objects = vec![
+        quad(
+            Vec3([555.0, 0.0, 0.0]),
+            Vec3([0.0, 555.0, 0.0]),
+            Vec3([0.0, 0.0, 555.0]),
+            "green",
+        ),
+        quad(
+            Vec3([0.0, 0.0, 0.0]),
+            Vec3([0.0, 555.0, 0.0]),
+            Vec3([0.0, 0.0, 555.0]),
+            "red",
+        ),
+        quad(
+            Vec3([113.0, 554.0, 127.0]),
+            Vec3([330.0, 0.0, 0.0]),
+            Vec3([0.0, 0.0, 305.0]),
+            "light",
+        ),
+        quad(
+            Vec3([0.0, 555.0, 0.0]),
+            Vec3([555.0, 0.0, 0.0]),
+            Vec3([0.0, 0.0, 555.0]),
+            "white",
+        ),
+        quad(
+            Vec3([0.0, 0.0, 0.0]),
+            Vec3([555.0, 0.0, 0.0]),
+            Vec3([0.0, 0.0, 555.0]),
+            "white",
+        ),
+        quad(
+            Vec3([0.0, 0.0, 555.0]),
+            Vec3([555.0, 0.0, 0.0]),
+            Vec3([0.0, 555.0, 0.0]),
+            "white",
+        ),
+        constant_medium(
+            translate(
+                rotate_y(
+                    make_box(Vec3([0.0, 0.0, 0.0]), Vec3([165.0, 330.0, 165.0]), "white"),
+                    15.0,
+                ),
+                Vec3([265.0, 0.0, 295.0]),
+            ),
+            0.01,
+            "smoke",
+        ),
+        constant_medium(
+            translate(
+                rotate_y(
+                    make_box(Vec3([0.0, 0.0, 0.0]), Vec3([165.0, 165.0, 165.0]), "white"),
+                    -18.0,
+                ),
+                Vec3([130.0, 0.0, 65.0]),
+            ),
+            0.01,
+            "fog",
+        ),
+    ];
+
+    Scene {
+        camera: cornell_camera(),
+        materials,
+        objects,
+        ..Default::default()
+    }
+}
+
+fn fancy(image_width: u32, samples: u32, max_depth: u32) -> Scene {
+    let mut rand = SmallRng::seed_from_u64(LAYOUT_SEED);
+
+    let materials = materials([
+        ("ground", lambertian(Vec3([0.48, 0.83, 0.53]))),
+        (
+            "light",
+            MaterialDescription::DiffuseLight {
+                texture: solid(Vec3([7.0, 7.0, 7.0])),
+            },
+        ),
+        (
+            "glass",
+            MaterialDescription::Dielectric {
+                refraction_index: 1.5,
+            },
+        ),
+        (
+            "metal",
+            MaterialDescription::Metal {
+                albedo: Vec3([0.8, 0.8, 0.9]),
+                fuzz: 1.0,
+            },
+        ),
+        (
+            "blue-medium",
+            MaterialDescription::Isotropic {
+                texture: solid(Vec3([0.2, 0.4, 0.9])),
+            },
+        ),
+        (
+            "mist",
+            MaterialDescription::Isotropic {
+                texture: solid(Vec3([1.0, 1.0, 1.0])),
+            },
+        ),
+        (
+            "earth",
+            MaterialDescription::Lambertian {
+                texture: TextureDescription::Image {
+                    path: PathBuf::from("earthmap.jpg"),
+                },
+            },
+        ),
+        (
+            "marble",
+            MaterialDescription::Lambertian {
+                texture: TextureDescription::Noise {
+                    scale: 0.2,
+                    seed: None,
+                },
+            },
+        ),
+        ("orange", lambertian(Vec3([0.7, 0.3, 0.1]))),
+        ("white", lambertian(Vec3::scalar(0.73))),
+    ]);
+
+    let mut boxes = Vec::new();
+    for i in 0..20 {
+        for j in 0..20 {
+            let w = 100.0;
+            let x0 = -1000.0 + i as f64 * w;
+            let z0 = -1000.0 + j as f64 * w;
+            let y0 = 0.0;
+            let x1 = x0 + w;
+            let y1 = rand.gen::<f64>() * 100.0 + 1.0;
+            let z1 = z0 + w;
+
+            boxes.push(make_box(Vec3([x0, y0, z0]), Vec3([x1, y1, z1]), "ground"));
+        }
+    }
+
+    let center1 = Vec3::scalar(400.0);
+    let center2 = center1 + Vec3([30.0, 0.0, 0.0]);
+
+    let mut boxes2 = Vec::new();
+    for _ in 0..1000 {
+        boxes2.push(sphere(
+            Vec3::random_within(0.0, 165.0, &mut rand),
+            10.0,
+            "white",
+        ));
+    }
+
+    let objects = vec![
+        ObjectDescription::Group { objects: boxes },
+        quad(
+            Vec3([123.0, 554.0, 147.0]),
+            Vec3([300.0, 0.0, 0.0]),
+            Vec3([0.0, 0.0, 265.0]),
+            "light",
+        ),
+        sphere(Vec3([260.0, 150.0, 45.0]), 50.0, "glass"),
+        sphere(Vec3([0.0, 150.0, 145.0]), 50.0, "metal"),
+        sphere(Vec3([360.0, 150.0, 145.0]), 70.0, "glass"),
+        constant_medium(
+            sphere(Vec3([360.0, 150.0, 145.0]), 70.0, "glass"),
+            0.2,
+            "blue-medium",
+        ),
+        constant_medium(
+            sphere(Vec3([0.0, 0.0, 0.0]), 5000.0, "glass"),
+            0.0001,
+            "mist",
+        ),
+        sphere(Vec3([400.0, 200.0, 400.0]), 100.0, "earth"),
+        sphere(Vec3([220.0, 280.0, 300.0]), 80.0, "marble"),
+        ObjectDescription::MovingSphere {
+            start: center1,
+            end: center2,
+            radius: 50.0,
+            material: "orange".to_string(),
+        },
+        translate(
+            rotate_y(ObjectDescription::Group { objects: boxes2 }, 15.0),
+            Vec3([-100.0, 270.0, 395.0]),
+        ),
+    ];
+
+    let camera = Camera::builder()
+        .aspect_ratio(1.0)
+        .image_width(image_width)
+        .samples_per_pixel(samples)
+        .max_depth(max_depth)
+        .vfov(40.0)
+        .look_from(Vec3([478.0, 278.0, -600.0]))
+        .look_at(Vec3([278.0, 278.0, 0.0]))
+        .background(Vec3([0.0, 0.0, 0.0]))
+        .sampler(SamplerKind::Sobol);
+
+    Scene {
+        camera,
+        materials,
+        objects,
+        ..Default::default()
+    }
+}
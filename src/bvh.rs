@@ -1,85 +1,371 @@
 use crate::aabb::Aabb;
-use crate::hittable::{HitRecord, Hittable};
+use crate::hittable::{HitRecord, Hittable, Transform};
 use crate::interval::Interval;
 use crate::ray::Ray;
+use crate::stats::{self, BvhVisit, Counter};
+use crate::vec3::Vec3;
+use rand::rngs::SmallRng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
 
+/// Buckets the centroids are sorted into along each axis when looking for the cheapest split.
+const BINS: usize = 16;
+
+/// The cost of testing a ray against a node's box, relative to testing it against a primitive.
+const TRAVERSAL_COST: f64 = 1.0;
+
+/// The deepest a tree may grow, which bounds the stack a traversal needs. Below half of this the
+/// builder splits at the median, which reaches any leaf size within the other half.
+const MAX_DEPTH: usize = 64;
+
+/// Subtrees over at least this many primitives build their two halves on separate threads.
+const PARALLEL_PRIMITIVES: usize = 4096;
+
+/// How a BVH decides where to split its primitives.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Split {
+    /// Halves the primitives along the longest axis, as in the book.
+    Median,
+    /// Picks the split with the lowest surface area heuristic cost among binned candidates, and
+    /// makes a leaf when no split is cheaper than testing every primitive.
+    #[default]
+    Sah,
+}
+
+/// How the BVHs over a scene and its meshes are built. These only change how fast a render is,
+/// never what it looks like.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub split: Split,
+    /// The most primitives a leaf may hold.
+    pub leaf_size: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            split: Split::default(),
+            leaf_size: 4,
+        }
+    }
+}
+
+/// A BVH flattened into one array of nodes in depth-first order, so each interior node's first
+/// child directly follows it, with the primitives of every leaf stored contiguously.
 pub struct Node {
-    left: Box<dyn Hittable>,
-    right: Option<Box<dyn Hittable>>,
+    nodes: Vec<LinearNode>,
+    primitives: Vec<Box<dyn Hittable>>,
+}
+
+/// A node packed into whole cache lines.
+#[repr(C, align(32))]
+struct LinearNode {
+    bounds: Aabb,
+    /// The first primitive of a leaf, or the second child of an interior node.
+    offset: u32,
+    /// The primitives in a leaf, or zero for an interior node.
+    count: u32,
+    /// The axis an interior node was split along, which decides which child a ray meets first.
+    axis: u8,
+}
+
+/// An object being sorted into the tree, with the bounds the split decisions need.
+struct Primitive {
+    object: Box<dyn Hittable>,
     bounds: Aabb,
+    centroid: Vec3,
 }
 
 impl Node {
-    pub fn from_list(mut objects: Vec<Box<dyn Hittable>>) -> Node {
+    pub fn from_list(objects: Vec<Box<dyn Hittable>>, settings: &Settings) -> Node {
+        let start = Instant::now();
+        let primitives: Vec<Primitive> = objects
+            .into_par_iter()
+            .map(|object| {
+                let bounds = object.bounding_box();
+                Primitive {
+                    centroid: bounds.centroid(),
+                    bounds,
+                    object,
+                }
+            })
+            .collect();
+
+        let node = Node::subtree(primitives, settings, 0);
+        stats::time(Counter::BuildNanos, start);
+
+        node
+    }
+
+    /// Builds the subtree over `primitives` into arrays of its own.
+    fn subtree(primitives: Vec<Primitive>, settings: &Settings, depth: usize) -> Node {
+        let mut node = Node {
+            nodes: Vec::with_capacity(2 * primitives.len()),
+            primitives: Vec::with_capacity(primitives.len()),
+        };
+        node.build(primitives, settings, depth);
+
+        node
+    }
+
+    /// Appends a subtree built by [`Node::subtree`], moving its offsets past what is already here.
+    fn append(&mut self, subtree: Node) {
+        let nodes = self.nodes.len() as u32;
+        let primitives = self.primitives.len() as u32;
+        self.nodes.extend(subtree.nodes.into_iter().map(|mut node| {
+            node.offset += if node.count > 0 { primitives } else { nodes };
+            node
+        }));
+        self.primitives.extend(subtree.primitives);
+    }
+
+    /// Appends the subtree over `primitives` to the arrays.
+    fn build(&mut self, mut primitives: Vec<Primitive>, settings: &Settings, depth: usize) {
         let mut bounds = Aabb::new(Interval::empty(), Interval::empty(), Interval::empty());
-        for object in &objects {
-            bounds = Aabb::from_bounds(bounds, object.bounding_box())
+        for primitive in &primitives {
+            bounds = Aabb::from_bounds(bounds, primitive.bounds.clone())
         }
 
-        let (left, right) = if objects.len() == 1 {
-            (objects.pop().unwrap(), None)
-        } else if objects.len() == 2 {
-            let right = objects.pop().unwrap();
-            let left = objects.pop().unwrap();
-            (left, Some(right))
-        } else {
-            let axis = bounds.longest_axis();
-
-            objects.sort_unstable_by(|l, r| match axis {
-                0 => Self::box_compare(l.as_ref(), r.as_ref(), 0),
-                1 => Self::box_compare(l.as_ref(), r.as_ref(), 1),
-                _ => Self::box_compare(l.as_ref(), r.as_ref(), 2),
-            });
-
-            let mid = objects.len() / 2;
-            let left: Box<dyn Hittable> =
-                Box::new(Node::from_list(objects.drain(0..mid).collect()));
-            let right: Box<dyn Hittable> = Box::new(Node::from_list(objects));
-
-            (left, Some(right))
+        let leaf_size = settings.leaf_size.max(1);
+        let split = match settings.split {
+            Split::Sah if depth < MAX_DEPTH / 2 => {
+                Node::sah_split(&mut primitives, &bounds, leaf_size)
+            }
+            _ => Node::median_split(&mut primitives, &bounds, leaf_size),
         };
 
-        Node {
-            left,
-            right,
+        let index = self.nodes.len();
+        self.nodes.push(LinearNode {
             bounds,
+            offset: self.primitives.len() as u32,
+            count: primitives.len() as u32,
+            axis: 0,
+        });
+
+        match split {
+            Some((axis, mid)) => {
+                let right = primitives.split_off(mid);
+                let second = if primitives.len() + right.len() >= PARALLEL_PRIMITIVES {
+                    let (left, right) = rayon::join(
+                        || Node::subtree(primitives, settings, depth + 1),
+                        || Node::subtree(right, settings, depth + 1),
+                    );
+                    self.append(left);
+                    let second = self.nodes.len() as u32;
+                    self.append(right);
+                    second
+                } else {
+                    self.build(primitives, settings, depth + 1);
+                    let second = self.nodes.len() as u32;
+                    self.build(right, settings, depth + 1);
+                    second
+                };
+
+                let node = &mut self.nodes[index];
+                node.offset = second;
+                node.count = 0;
+                node.axis = axis;
+            }
+            None => self
+                .primitives
+                .extend(primitives.into_iter().map(|primitive| primitive.object)),
+        }
+    }
+
+    /// Splits the primitives in half along the longest axis once there are more than fit in a
+    /// leaf. Returns the axis and how many primitives go in the first half.
+    fn median_split(
+        primitives: &mut [Primitive],
+        bounds: &Aabb,
+        leaf_size: usize,
+    ) -> Option<(u8, usize)> {
+        if primitives.len() <= leaf_size {
+            return None;
         }
+
+        let axis = bounds.longest_axis();
+        let mid = primitives.len() / 2;
+        primitives
+            .select_nth_unstable_by(mid, |l, r| Self::box_compare(&l.bounds, &r.bounds, axis));
+
+        Some((axis as u8, mid))
     }
 
-    fn box_compare(a: &dyn Hittable, b: &dyn Hittable, axis: u32) -> Ordering {
-        let a_axis_interval = a.bounding_box().axis_interval(axis);
-        let b_axis_interval = b.bounding_box().axis_interval(axis);
+    fn box_compare(a: &Aabb, b: &Aabb, axis: u32) -> Ordering {
+        let a_axis_interval = a.axis_interval(axis);
+        let b_axis_interval = b.axis_interval(axis);
 
         a_axis_interval
             .min
             .partial_cmp(&b_axis_interval.min)
             .unwrap()
     }
-}
 
-impl Hittable for Node {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
-        if !self.bounds.hit(ray, ray_t) {
+    /// Moves the primitives on the near side of the cheapest split to the front and returns the
+    /// split's axis and how many there are, or `None` if they are cheaper to test as a leaf.
+    fn sah_split(
+        primitives: &mut [Primitive],
+        bounds: &Aabb,
+        leaf_size: usize,
+    ) -> Option<(u8, usize)> {
+        let count = primitives.len();
+        if count == 1 {
             return None;
         }
 
-        if let Some(hit_left) = self.left.hit(ray, ray_t) {
-            if let Some(right) = &self.right {
-                right
-                    .hit(ray, Interval::new(ray_t.min, hit_left.t))
-                    .or(Some(hit_left))
-            } else {
-                Some(hit_left)
+        let mut low = Vec3::scalar(f64::INFINITY);
+        let mut high = Vec3::scalar(f64::NEG_INFINITY);
+        for primitive in primitives.iter() {
+            for axis in 0..3 {
+                low.0[axis] = low[axis].min(primitive.centroid[axis]);
+                high.0[axis] = high[axis].max(primitive.centroid[axis]);
+            }
+        }
+
+        let bin = |primitive: &Primitive, axis: usize| {
+            let offset = (primitive.centroid[axis] - low[axis]) / (high[axis] - low[axis]);
+            ((offset * BINS as f64) as usize).min(BINS - 1)
+        };
+
+        // The cheapest split as its cost, axis and the last bin on its near side.
+        let mut best: Option<(f64, usize, usize)> = None;
+        for axis in 0..3 {
+            if high[axis] <= low[axis] {
+                continue;
+            }
+
+            let mut bins: [(usize, Option<Aabb>); BINS] = std::array::from_fn(|_| (0, None));
+            for primitive in primitives.iter() {
+                let (count, bin_bounds) = &mut bins[bin(primitive, axis)];
+                *count += 1;
+                *bin_bounds = Some(merge(bin_bounds.take(), &primitive.bounds));
+            }
+
+            // The primitives beyond each candidate split and the area of their bounds.
+            let mut far = [(0, 0.0); BINS - 1];
+            let (mut far_count, mut far_bounds) = (0, None);
+            for split in (0..BINS - 1).rev() {
+                let (count, bin_bounds) = &bins[split + 1];
+                far_count += count;
+                if let Some(bin_bounds) = bin_bounds {
+                    far_bounds = Some(merge(far_bounds, bin_bounds));
+                }
+                far[split] = (
+                    far_count,
+                    far_bounds.as_ref().map_or(0.0, Aabb::surface_area),
+                );
+            }
+
+            let (mut near_count, mut near_bounds) = (0, None);
+            for (split, (count, bin_bounds)) in bins[..BINS - 1].iter().enumerate() {
+                near_count += count;
+                if let Some(bin_bounds) = bin_bounds {
+                    near_bounds = Some(merge(near_bounds, bin_bounds));
+                }
+
+                let (far_count, far_area) = far[split];
+                if near_count == 0 || far_count == 0 {
+                    continue;
+                }
+
+                let near_area = near_bounds.as_ref().map_or(0.0, Aabb::surface_area);
+                let cost = TRAVERSAL_COST
+                    + (near_count as f64 * near_area + far_count as f64 * far_area)
+                        / bounds.surface_area();
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, split));
+                }
+            }
+        }
+
+        match best {
+            Some((cost, axis, split)) if count > leaf_size || cost < count as f64 => {
+                let mut mid = 0;
+                for i in 0..count {
+                    if bin(&primitives[i], axis) <= split {
+                        primitives.swap(i, mid);
+                        mid += 1;
+                    }
+                }
+                Some((axis as u8, mid))
             }
-        } else if let Some(right) = &self.right {
-            right.hit(ray, ray_t)
-        } else {
-            None
+            // Every centroid is in the same place, so any split is as good as another.
+            None if count > leaf_size => Some((0, count / 2)),
+            _ => None,
+        }
+    }
+}
+
+fn merge(bounds: Option<Aabb>, other: &Aabb) -> Aabb {
+    match bounds {
+        Some(bounds) => Aabb::from_bounds(bounds, other.clone()),
+        None => other.clone(),
+    }
+}
+
+impl Hittable for Node {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rng: &mut SmallRng) -> Option<HitRecord<'_>> {
+        let visit = BvhVisit::enter();
+        let negative = [
+            ray.direction[0] < 0.0,
+            ray.direction[1] < 0.0,
+            ray.direction[2] < 0.0,
+        ];
+
+        let mut hit = None;
+        let mut closest_so_far = ray_t.max;
+        // The nodes still to visit and their depths, nearest on top.
+        let mut stack = [(0, 0); MAX_DEPTH];
+        let mut stack_len = 0;
+        let (mut index, mut depth) = (0, 0);
+        loop {
+            let node = &self.nodes[index];
+            visit.node(depth);
+            if node
+                .bounds
+                .hit(ray, Interval::new(ray_t.min, closest_so_far))
+            {
+                if node.count > 0 {
+                    let start = node.offset as usize;
+                    for primitive in &self.primitives[start..start + node.count as usize] {
+                        let interval = Interval::new(ray_t.min, closest_so_far);
+                        if let Some(record) = primitive.hit(ray, interval, rng) {
+                            closest_so_far = record.t;
+                            hit = Some(record);
+                        }
+                    }
+                } else {
+                    let (near, far) = if negative[node.axis as usize] {
+                        (node.offset as usize, index + 1)
+                    } else {
+                        (index + 1, node.offset as usize)
+                    };
+                    stack[stack_len] = (far, depth + 1);
+                    stack_len += 1;
+                    (index, depth) = (near, depth + 1);
+                    continue;
+                }
+            }
+
+            if stack_len == 0 {
+                return hit;
+            }
+            stack_len -= 1;
+            (index, depth) = stack[stack_len];
         }
     }
 
     fn bounding_box(&self) -> Aabb {
-        self.bounds.clone()
+        self.nodes[0].bounds.clone()
     }
 }
+
+/// A placement of a BVH built once and shared between any number of instances, each with its own
+/// affine transform. A BVH over instances makes a two-level structure whose memory grows with the
+/// unique geometry rather than the number of copies.
+pub type Instance = Transform<Arc<Node>>;
@@ -1,10 +1,24 @@
+use crate::film::{Film, FilmSample, Pixel};
+use crate::filter::{Filter, FilterKind};
 use crate::hittable::Hittable;
+use crate::progress::{Progress, Tracker};
 use crate::ray::Ray;
+use crate::sampler::{self, concentric_disk, SampleStream, Sampler, SamplerKind};
+use crate::stats::{self, Counter};
+use crate::tile::{self, Tile, TileOrder};
+use crate::tonemap::DisplayTransform;
 use crate::vec3::Vec3;
 use image::RgbImage;
-use rand::Rng;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
 use rayon::prelude::*;
-use std::sync::atomic::{AtomicU32, Ordering};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// How many samples adaptive sampling takes between convergence tests.
+const ADAPTIVE_STEP: u32 = 8;
 
 pub struct Camera {
     image_width: u32,
@@ -19,35 +33,330 @@ pub struct Camera {
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
     background: Vec3,
+    min_samples_per_pixel: u32,
+    adaptive_threshold: f64,
+    seed: u64,
+    tile_size: u32,
+    tile_order: TileOrder,
+    crop: Tile,
+    sampler: Box<dyn Sampler>,
+    filter: Box<dyn Filter>,
+    progress: Mutex<Option<ProgressCallback>>,
 }
 
-impl Camera {
-    //todo builder?
-    #[allow(clippy::too_many_arguments)]
-    pub fn new(
-        aspect_ratio: f64,
-        image_width: u32,
-        samples_per_pixel: u32,
-        max_depth: u32,
-        vfov: f64,
-        look_from: Vec3,
-        look_at: Vec3,
-        vup: Vec3,
-        defocus_angle: f64,
-        focus_dist: f64,
-        background: Vec3,
-    ) -> Self {
-        let image_height = ((image_width as f64 / aspect_ratio) as u32).max(1);
-
-        let center = look_from;
-
-        let theta = vfov.to_radians();
+type ProgressCallback = Box<dyn FnMut(&Progress) + Send>;
+
+/// Configures a [`Camera`] through named setters. Unset values fall back to the defaults used
+/// throughout the book, and [`CameraBuilder::build`] rejects configurations that would render NaNs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraBuilder {
+    aspect_ratio: f64,
+    image_width: u32,
+    samples_per_pixel: u32,
+    max_depth: u32,
+    vfov: f64,
+    look_from: Vec3,
+    look_at: Vec3,
+    vup: Vec3,
+    defocus_angle: f64,
+    focus_dist: f64,
+    background: Vec3,
+    min_samples_per_pixel: u32,
+    adaptive_threshold: f64,
+    seed: u64,
+    tile_size: u32,
+    tile_order: TileOrder,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    crop: Option<Crop>,
+    sampler: SamplerKind,
+    filter: FilterKind,
+}
+
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        CameraBuilder {
+            aspect_ratio: 1.0,
+            image_width: 100,
+            samples_per_pixel: 10,
+            max_depth: 10,
+            vfov: 90.0,
+            look_from: Vec3([0.0, 0.0, 0.0]),
+            look_at: Vec3([0.0, 0.0, -1.0]),
+            vup: Vec3([0.0, 1.0, 0.0]),
+            defocus_angle: 0.0,
+            focus_dist: 10.0,
+            background: Vec3([0.7, 0.8, 1.0]),
+            min_samples_per_pixel: 16,
+            adaptive_threshold: 0.0,
+            seed: 0,
+            tile_size: 16,
+            tile_order: TileOrder::Scanline,
+            crop: None,
+            sampler: SamplerKind::Independent,
+            filter: FilterKind::Box,
+        }
+    }
+}
+
+/// A window of the image, from `[x0, y0]` up to but not including `[x1, y1]`, either in pixels or
+/// as fractions of the image's width and height.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Crop {
+    Pixels([u32; 4]),
+    Normalized([f64; 4]),
+}
+
+impl Crop {
+    /// The pixels covered in a `width` by `height` image, rounded outwards, if the window is
+    /// inside the image and not empty.
+    pub fn window(&self, width: u32, height: u32) -> Option<Tile> {
+        let [x0, y0, x1, y1] = match *self {
+            Crop::Pixels(window) => window,
+            Crop::Normalized(window) => {
+                if !window.iter().all(|c| (0.0..=1.0).contains(c)) {
+                    return None;
+                }
+
+                let [x0, y0, x1, y1] = window;
+                [
+                    (x0 * width as f64).floor() as u32,
+                    (y0 * height as f64).floor() as u32,
+                    (x1 * width as f64).ceil() as u32,
+                    (y1 * height as f64).ceil() as u32,
+                ]
+            }
+        };
+
+        (x0 < x1 && y0 < y1 && x1 <= width && y1 <= height).then_some(Tile { x0, y0, x1, y1 })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    ZeroWidth,
+    ZeroSamples,
+    ZeroTileSize,
+    InvalidAspectRatio(f64),
+    InvalidFieldOfView(f64),
+    InvalidFocusDistance(f64),
+    InvalidAdaptiveThreshold(f64),
+    InvalidCrop(Crop),
+    DegenerateView,
+    UpParallelToView,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::ZeroWidth => write!(f, "image width must be at least one pixel"),
+            Error::ZeroSamples => write!(f, "at least one sample per pixel is required"),
+            Error::ZeroTileSize => write!(f, "tiles must be at least one pixel wide"),
+            Error::InvalidAspectRatio(ratio) => {
+                write!(f, "aspect ratio must be positive, got {ratio}")
+            }
+            Error::InvalidFieldOfView(vfov) => {
+                write!(
+                    f,
+                    "vertical field of view must be between 0 and 180 degrees, got {vfov}"
+                )
+            }
+            Error::InvalidFocusDistance(dist) => {
+                write!(f, "focus distance must be positive, got {dist}")
+            }
+            Error::InvalidAdaptiveThreshold(threshold) => {
+                write!(
+                    f,
+                    "adaptive threshold must be zero or positive, got {threshold}"
+                )
+            }
+            Error::InvalidCrop(crop) => {
+                write!(
+                    f,
+                    "crop window must be a non-empty part of the image, got {crop:?}"
+                )
+            }
+            Error::DegenerateView => write!(f, "look_from and look_at must be different points"),
+            Error::UpParallelToView => write!(f, "vup must not be parallel to the view direction"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl CameraBuilder {
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    pub fn image_width(mut self, image_width: u32) -> Self {
+        self.image_width = image_width;
+        self
+    }
+
+    pub fn samples_per_pixel(mut self, samples_per_pixel: u32) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Vertical field of view in degrees.
+    pub fn vfov(mut self, vfov: f64) -> Self {
+        self.vfov = vfov;
+        self
+    }
+
+    pub fn look_from(mut self, look_from: Vec3) -> Self {
+        self.look_from = look_from;
+        self
+    }
+
+    pub fn look_at(mut self, look_at: Vec3) -> Self {
+        self.look_at = look_at;
+        self
+    }
+
+    pub fn vup(mut self, vup: Vec3) -> Self {
+        self.vup = vup;
+        self
+    }
+
+    /// Angle of the cone from the focus plane to the lens, in degrees. Zero disables depth of field.
+    pub fn defocus_angle(mut self, defocus_angle: f64) -> Self {
+        self.defocus_angle = defocus_angle;
+        self
+    }
+
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = focus_dist;
+        self
+    }
+
+    pub fn background(mut self, background: Vec3) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// With adaptive sampling, the fewest samples a pixel takes before it may be found converged.
+    pub fn min_samples_per_pixel(mut self, min_samples_per_pixel: u32) -> Self {
+        self.min_samples_per_pixel = min_samples_per_pixel;
+        self
+    }
+
+    /// Stops sampling pixels once their [`Pixel::relative_error`] is below this, with
+    /// `samples_per_pixel` as the most any pixel takes. Zero samples every pixel fully.
+    pub fn adaptive_threshold(mut self, adaptive_threshold: f64) -> Self {
+        self.adaptive_threshold = adaptive_threshold;
+        self
+    }
+
+    /// Seeds every random decision made while rendering. Renders with the same seed are
+    /// identical however the work is spread across threads.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The width and height of the square tiles the image is rendered in. Each tile is traced by
+    /// one thread, so rays from neighbouring pixels share cached parts of the BVH.
+    pub fn tile_size(mut self, tile_size: u32) -> Self {
+        self.tile_size = tile_size;
+        self
+    }
+
+    /// The order tiles are rendered and finished in.
+    pub fn tile_order(mut self, tile_order: TileOrder) -> Self {
+        self.tile_order = tile_order;
+        self
+    }
+
+    /// Renders only this window of the image. Rays are generated exactly as for the whole
+    /// image, so the window matches the same pixels of a full render.
+    pub fn crop(mut self, crop: Option<Crop>) -> Self {
+        self.crop = crop;
+        self
+    }
+
+    /// Where pixel positions, lens positions, times and bounce directions come from.
+    pub fn sampler(mut self, sampler: SamplerKind) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// How samples are weighed into the pixels around them.
+    pub fn filter(mut self, filter: FilterKind) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn build(&self) -> Result<Camera, Error> {
+        if self.image_width == 0 {
+            return Err(Error::ZeroWidth);
+        }
+
+        if self.samples_per_pixel == 0 {
+            return Err(Error::ZeroSamples);
+        }
+
+        if self.tile_size == 0 {
+            return Err(Error::ZeroTileSize);
+        }
+
+        if !(self.aspect_ratio.is_finite() && self.aspect_ratio > 0.0) {
+            return Err(Error::InvalidAspectRatio(self.aspect_ratio));
+        }
+
+        if !(self.vfov > 0.0 && self.vfov < 180.0) {
+            return Err(Error::InvalidFieldOfView(self.vfov));
+        }
+
+        if !(self.focus_dist.is_finite() && self.focus_dist > 0.0) {
+            return Err(Error::InvalidFocusDistance(self.focus_dist));
+        }
+
+        if !(self.adaptive_threshold.is_finite() && self.adaptive_threshold >= 0.0) {
+            return Err(Error::InvalidAdaptiveThreshold(self.adaptive_threshold));
+        }
+
+        let view = self.look_from - self.look_at;
+        if view.near_zero() {
+            return Err(Error::DegenerateView);
+        }
+
+        if self.vup.cross(view).near_zero() {
+            return Err(Error::UpParallelToView);
+        }
+
+        let image_width = self.image_width;
+        let image_height = ((image_width as f64 / self.aspect_ratio) as u32).max(1);
+
+        let crop = match self.crop {
+            Some(crop) => crop
+                .window(image_width, image_height)
+                .ok_or(Error::InvalidCrop(crop))?,
+            None => Tile {
+                x0: 0,
+                y0: 0,
+                x1: image_width,
+                y1: image_height,
+            },
+        };
+
+        let center = self.look_from;
+
+        let theta = self.vfov.to_radians();
         let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * focus_dist;
+        let viewport_height = 2.0 * h * self.focus_dist;
         let viewport_width = viewport_height * (image_width as f64 / image_height as f64);
 
-        let w = (look_from - look_at).unit();
-        let u = vup.cross(w).unit();
+        let w = view.unit();
+        let u = self.vup.cross(w).unit();
         let v = w.cross(u);
 
         let viewport_u = viewport_width * u;
@@ -56,83 +365,381 @@ impl Camera {
         let pixel_delta_u = viewport_u / image_width as f64;
         let pixel_delta_v = viewport_v / image_height as f64;
 
-        let viewport_upper_left = center - (focus_dist * w) - viewport_u / 2.0 - viewport_v / 2.0;
+        let viewport_upper_left =
+            center - (self.focus_dist * w) - viewport_u / 2.0 - viewport_v / 2.0;
         let pixel00_loc = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
 
-        let defocus_radius = focus_dist * (defocus_angle / 2.0).to_radians().tan();
+        let defocus_radius = self.focus_dist * (self.defocus_angle / 2.0).to_radians().tan();
         let defocus_disk_u = u * defocus_radius;
         let defocus_disk_v = v * defocus_radius;
 
-        Camera {
+        Ok(Camera {
             image_width,
             image_height,
             center,
             pixel00_loc,
             pixel_delta_u,
             pixel_delta_v,
-            samples_per_pixel,
-            max_depth,
-            defocus_angle,
+            samples_per_pixel: self.samples_per_pixel,
+            max_depth: self.max_depth,
+            defocus_angle: self.defocus_angle,
             defocus_disk_u,
             defocus_disk_v,
-            background,
+            background: self.background,
+            min_samples_per_pixel: self.min_samples_per_pixel,
+            adaptive_threshold: self.adaptive_threshold,
+            seed: self.seed,
+            tile_size: self.tile_size,
+            tile_order: self.tile_order,
+            crop,
+            sampler: self.sampler.build(self.samples_per_pixel, self.seed),
+            filter: self.filter.build(),
+            progress: Mutex::new(None),
+        })
+    }
+}
+
+impl Camera {
+    pub fn builder() -> CameraBuilder {
+        CameraBuilder::default()
+    }
+
+    pub fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    /// The part of the image being rendered, which without a crop window is all of it.
+    pub fn crop(&self) -> Tile {
+        self.crop
+    }
+
+    /// Every tile to render, in order. These cover the crop window and the pixels around it the
+    /// filter spreads samples from, so the window's edges match a full render.
+    pub fn tiles(&self) -> Vec<Tile> {
+        tile::tiles(
+            self.footprint(&[self.crop]),
+            self.tile_size,
+            self.tile_order,
+        )
+    }
+
+    /// The smallest window holding every pixel the samples taken in `tiles` can land in.
+    pub fn footprint(&self, tiles: &[Tile]) -> Tile {
+        let reach = self.filter.reach();
+        tiles
+            .iter()
+            .map(|tile| Tile {
+                x0: tile.x0.saturating_sub(reach),
+                y0: tile.y0.saturating_sub(reach),
+                x1: (tile.x1 + reach).min(self.image_width),
+                y1: (tile.y1 + reach).min(self.image_height),
+            })
+            .reduce(|a, b| Tile {
+                x0: a.x0.min(b.x0),
+                y0: a.y0.min(b.y0),
+                x1: a.x1.max(b.x1),
+                y1: a.y1.max(b.y1),
+            })
+            .unwrap_or(Tile {
+                x0: 0,
+                y0: 0,
+                x1: 0,
+                y1: 0,
+            })
+    }
+
+    /// The width and height of the whole image in pixels.
+    pub fn resolution(&self) -> (u32, u32) {
+        (self.image_width, self.image_height)
+    }
+
+    /// An empty film matching this camera's resolution.
+    pub fn film(&self) -> Film {
+        Film::new(self.image_width, self.image_height)
+    }
+
+    /// Renders `world`, sampling the emitters in `lights` directly. Lights left out of `lights`
+    /// still work, they are just only found by chance.
+    pub fn render<H, L>(&self, world: &H, lights: &L) -> RgbImage
+    where
+        H: Hittable + ?Sized,
+        L: Hittable + ?Sized,
+    {
+        let mut film = self.film();
+        self.render_pass(world, lights, &mut film, self.samples_per_pixel);
+
+        film.cropped(self.crop)
+            .to_image(&DisplayTransform::default())
+    }
+
+    /// Calls `callback` with how far the render has come each time a batch of tiles has been
+    /// sampled, from whichever thread is driving the render.
+    pub fn on_progress(&mut self, callback: impl FnMut(&Progress) + Send + 'static) {
+        *self.progress.get_mut().unwrap() = Some(Box::new(callback));
+    }
+
+    /// Renders in passes of `pass_samples` until every pixel to render has `target` samples or,
+    /// with adaptive sampling, has converged, calling `on_pass` with the accumulated film after
+    /// each one. Progress is reported towards `target` across all passes.
+    pub fn render_progressive<H, L, F>(
+        &self,
+        world: &H,
+        lights: &L,
+        film: &mut Film,
+        target: u32,
+        pass_samples: u32,
+        mut on_pass: F,
+    ) where
+        H: Hittable + ?Sized,
+        L: Hittable + ?Sized,
+        F: FnMut(&Film),
+    {
+        let mut tracker = self.tracker(film, self.tiles(), target);
+        while !tracker.progress().done() {
+            let pass_target = (film.max_samples() + pass_samples.max(1)).min(target);
+            self.trace_tiles(world, lights, film, pass_target, &mut tracker, |_| {});
+            on_pass(film);
         }
     }
 
-    pub fn render<H: Hittable + ?Sized>(&self, world: &H) -> RgbImage {
-        let mut image = RgbImage::new(self.image_width, self.image_height);
-        let pixels = AtomicU32::new(0);
-        let total = self.image_width * self.image_height;
+    /// Samples every pixel to render until it has `target` samples or has converged.
+    pub fn render_pass<H, L>(&self, world: &H, lights: &L, film: &mut Film, target: u32)
+    where
+        H: Hittable + ?Sized,
+        L: Hittable + ?Sized,
+    {
+        let mut tracker = self.tracker(film, self.tiles(), target);
+        self.trace_tiles(world, lights, film, target, &mut tracker, |_| {});
+    }
+
+    /// Samples the pixels of `tiles` until each has `target` samples or has converged, calling
+    /// `on_tile` with every tile as it finishes.
+    pub fn render_tiles<H, L, F>(
+        &self,
+        world: &H,
+        lights: &L,
+        film: &mut Film,
+        target: u32,
+        tiles: &[Tile],
+        on_tile: F,
+    ) where
+        H: Hittable + ?Sized,
+        L: Hittable + ?Sized,
+        F: FnMut(&Tile),
+    {
+        let mut tracker = self.tracker(film, tiles.to_vec(), target);
+        self.trace_tiles(world, lights, film, target, &mut tracker, on_tile);
+    }
+
+    /// Samples the tracked tiles up to `target`. A window of tiles is traced in parallel, a batch
+    /// of samples at a time, and splatted through the filter before the next batch starts, so
+    /// memory stays bounded and tiles finish roughly in the order given. Progress is reported
+    /// after every batch.
+    fn trace_tiles<H, L, F>(
+        &self,
+        world: &H,
+        lights: &L,
+        film: &mut Film,
+        target: u32,
+        tracker: &mut Tracker,
+        mut on_tile: F,
+    ) where
+        H: Hittable + ?Sized,
+        L: Hittable + ?Sized,
+        F: FnMut(&Tile),
+    {
+        const MAX_BATCHED_SAMPLES: u32 = 1 << 22;
 
-        image.par_enumerate_pixels_mut().for_each(|(x, y, pixel)| {
-            let mut color = Vec3::scalar(0.0);
-            for _ in 0..self.samples_per_pixel {
-                let temp = self
-                    .get_ray(x, y)
-                    .color(self.max_depth, world, self.background);
-                color += temp;
+        let window = rayon::current_num_threads() * 4;
+        let width = film.width();
+        let tiles = tracker.tiles().to_vec();
+        let mut remaining = tiles.iter();
+        let mut active: Vec<Tile> = Vec::new();
+        let mut samples: Vec<Vec<FilmSample>> = vec![Vec::new(); film.pixels().len()];
+
+        loop {
+            while active.len() < window {
+                let Some(tile) = remaining.next() else {
+                    break;
+                };
+                if self.tile_done(film, tile, target) {
+                    on_tile(tile);
+                } else {
+                    active.push(*tile);
+                }
+            }
+
+            if active.is_empty() {
+                break;
             }
 
-            *pixel = (color / self.samples_per_pixel as f64).into();
+            let area: u64 = active.iter().map(Tile::area).sum();
+            let batch = (u64::from(MAX_BATCHED_SAMPLES) / area).max(1) as u32;
+            let start = Instant::now();
+            let traced: Vec<Vec<(usize, Vec<FilmSample>)>> = active
+                .par_iter()
+                .map(|tile| {
+                    let traced = tile
+                        .pixels()
+                        .map(|(x, y)| {
+                            let i = (y * width + x) as usize;
+                            let pixel = &film.pixels()[i];
+                            (
+                                i,
+                                self.trace_pixel(world, lights, pixel, (x, y), target, batch),
+                            )
+                        })
+                        .collect();
+                    stats::flush();
+
+                    traced
+                })
+                .collect();
+
+            for (i, traced) in traced.into_iter().flatten() {
+                samples[i] = traced;
+            }
+            film.splat(&samples, &active, self.filter.as_ref());
+            for (x, y) in active.iter().flat_map(Tile::pixels) {
+                samples[(y * width + x) as usize] = Vec::new();
+            }
+            stats::time(Counter::RenderNanos, start);
+
+            for tile in &active {
+                tracker.update(tile, self.tile_work(film, tile, tracker.target()));
+            }
+            self.report(&tracker.progress());
+
+            active.retain(|tile| {
+                let done = self.tile_done(film, tile, target);
+                if done {
+                    on_tile(tile);
+                }
+                !done
+            });
+        }
+    }
+
+    pub(crate) fn tracker(&self, film: &Film, tiles: Vec<Tile>, target: u32) -> Tracker {
+        Tracker::new(tiles, target, |tile| self.tile_work(film, tile, target))
+    }
+
+    /// The samples the pixels of `tile` have, and how many more they need to reach `target`.
+    pub(crate) fn tile_work(&self, film: &Film, tile: &Tile, target: u32) -> (u64, u64) {
+        tile.pixels()
+            .map(|(x, y)| film.pixel(x, y))
+            .fold((0, 0), |(samples, remaining), pixel| {
+                let needed = if self.needs_samples(pixel, target) {
+                    target - pixel.samples
+                } else {
+                    0
+                };
+                (samples + pixel.samples as u64, remaining + needed as u64)
+            })
+    }
+
+    pub(crate) fn report(&self, progress: &Progress) {
+        if let Some(callback) = self.progress.lock().unwrap().as_mut() {
+            callback(progress);
+        }
+    }
+
+    /// Traces the next batch of up to `batch` samples of the pixel at `(x, y)`. With adaptive
+    /// sampling, the batch ends at the next convergence test.
+    fn trace_pixel<H, L>(
+        &self,
+        world: &H,
+        lights: &L,
+        pixel: &Pixel,
+        (x, y): (u32, u32),
+        target: u32,
+        batch: u32,
+    ) -> Vec<FilmSample>
+    where
+        H: Hittable + ?Sized,
+        L: Hittable + ?Sized,
+    {
+        if !self.needs_samples(pixel, target) {
+            return Vec::new();
+        }
 
-            let count = pixels.fetch_add(1, Ordering::Relaxed);
-            if count % 1000 == 0 {
-                let progress = (count as f64 / total as f64) * 100.0;
-                println!("{progress}");
-            };
-        });
+        let first = pixel.samples;
+        let mut count = batch.min(target - first);
+        if self.adaptive_threshold > 0.0 {
+            count = count.min(ADAPTIVE_STEP - first % ADAPTIVE_STEP);
+        }
 
-        image
+        stats::add(Counter::CameraRays, count as u64);
+        let index = y as u64 * self.image_width as u64 + x as u64;
+        (first..first + count)
+            .map(|sample| {
+                let rng = self.sample_rng(index, sample);
+                let mut samples = SampleStream::new(self.sampler.as_ref(), (x, y), sample, rng);
+                let offset = samples.get_2d();
+                let radiance = self.get_ray(x, y, offset, &mut samples).color(
+                    self.max_depth,
+                    world,
+                    lights,
+                    self.background,
+                    &mut samples,
+                );
+
+                FilmSample { offset, radiance }
+            })
+            .collect()
     }
 
-    fn get_ray(&self, x: u32, y: u32) -> Ray {
-        let offset = Camera::sample_square();
+    /// Whether every pixel of `tile` has `target` samples or has converged.
+    pub fn tile_done(&self, film: &Film, tile: &Tile, target: u32) -> bool {
+        tile.pixels()
+            .all(|(x, y)| !self.needs_samples(film.pixel(x, y), target))
+    }
+
+    /// Whether `pixel` should be sampled further on its way to `target` samples. With adaptive
+    /// sampling, convergence is only tested every [`ADAPTIVE_STEP`] samples, so where a pixel
+    /// stops does not depend on how the render was split into passes.
+    fn needs_samples(&self, pixel: &Pixel, target: u32) -> bool {
+        if pixel.samples >= target {
+            return false;
+        }
+
+        !(self.adaptive_threshold > 0.0
+            && pixel.samples >= self.min_samples_per_pixel
+            && pixel.samples.is_multiple_of(ADAPTIVE_STEP)
+            && pixel.relative_error() < self.adaptive_threshold)
+    }
+
+    /// The generator for whatever one sample of one pixel decides outside of the sampler's
+    /// dimensions. It depends only on the seed and on which sample this is, so passes, threads
+    /// and resumed renders all draw the same numbers.
+    fn sample_rng(&self, pixel: u64, sample: u32) -> SmallRng {
+        SmallRng::seed_from_u64(sampler::hash(self.seed, &[pixel, sample as u64]))
+    }
+
+    /// The ray through `offset` within pixel `(x, y)`. Takes the lens position and time from
+    /// `samples`, which has already handed out the offset.
+    fn get_ray(&self, x: u32, y: u32, offset: [f64; 2], samples: &mut SampleStream) -> Ray {
         let pixel_sample = self.pixel00_loc
-            + (x as f64 + offset[0]) * self.pixel_delta_u
-            + (y as f64 + offset[1]) * self.pixel_delta_v;
+            + (x as f64 + offset[0] - 0.5) * self.pixel_delta_u
+            + (y as f64 + offset[1] - 0.5) * self.pixel_delta_v;
+        let lens = samples.get_2d();
         let origin = if self.defocus_angle <= 0.0 {
             self.center
         } else {
-            self.defocus_disk_sample()
+            self.defocus_disk_sample(lens)
         };
 
         let direction = pixel_sample - origin;
 
-        let mut rand = rand::thread_rng();
         Ray {
             origin,
             direction,
-            time: rand.gen(),
+            time: samples.get_1d(),
         }
     }
 
-    fn sample_square() -> Vec3 {
-        let mut rng = rand::thread_rng();
-        Vec3([rng.gen::<f64>() - 0.5, rng.gen::<f64>() - 0.5, 0.0])
-    }
-
-    fn defocus_disk_sample(&self) -> Vec3 {
-        let p = Vec3::random_in_unit_disk();
+    fn defocus_disk_sample(&self, u: [f64; 2]) -> Vec3 {
+        let p = concentric_disk(u);
 
         self.center + (p[0] * self.defocus_disk_u) + (p[1] * self.defocus_disk_v)
     }
@@ -0,0 +1,143 @@
+use crate::camera::CameraBuilder;
+use crate::film::{Film, Pixel};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 8] = b"RTCKPT03";
+
+/// The state of an unfinished render: the radiance accumulated so far and what it was rendered
+/// from, so a later run can keep adding samples to it.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub camera: CameraBuilder,
+    pub scene_hash: u64,
+    pub film: Film,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    Format(PathBuf, String),
+    SceneMismatch,
+    CameraMismatch,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            Error::Format(path, message) => {
+                write!(f, "{}: not a valid checkpoint: {message}", path.display())
+            }
+            Error::SceneMismatch => write!(f, "checkpoint was rendered from a different scene"),
+            Error::CameraMismatch => write!(
+                f,
+                "checkpoint was rendered with different camera, sampling, tiling or crop settings"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Checkpoint {
+    /// Writes the checkpoint next to `path` first and then moves it into place, so a crash while
+    /// saving never destroys the previous checkpoint.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let camera = toml::to_string(&self.camera)
+            .map_err(|e| Error::Format(path.to_path_buf(), e.to_string()))?;
+
+        let mut bytes =
+            Vec::with_capacity(64 + camera.len() + self.film.pixels().len() * Pixel::ENCODED_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.scene_hash.to_le_bytes());
+        bytes.extend_from_slice(&(camera.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(camera.as_bytes());
+        bytes.extend_from_slice(&self.film.width().to_le_bytes());
+        bytes.extend_from_slice(&self.film.height().to_le_bytes());
+        for pixel in self.film.pixels() {
+            pixel
+                .write(&mut bytes)
+                .expect("writing to a Vec cannot fail");
+        }
+
+        let temp = path.with_extension("partial");
+        fs::File::create(&temp)
+            .and_then(|mut file| {
+                file.write_all(&bytes)?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&temp, path))
+            .map_err(|e| Error::Io(path.to_path_buf(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<Checkpoint, Error> {
+        let bytes = fs::read(path).map_err(|e| Error::Io(path.to_path_buf(), e))?;
+        let format = |message: &str| Error::Format(path.to_path_buf(), message.to_string());
+
+        let mut reader = bytes.as_slice();
+        let mut magic = [0; 8];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| format("file is too short"))?;
+        if &magic != MAGIC {
+            return Err(format("unrecognised header"));
+        }
+
+        let truncated = |_| format("file is truncated");
+        let scene_hash = u64::from_le_bytes(read_array(&mut reader).map_err(truncated)?);
+        let camera_len = u32::from_le_bytes(read_array(&mut reader).map_err(truncated)?) as usize;
+        if camera_len > reader.len() {
+            return Err(format("file is truncated"));
+        }
+        let mut camera = vec![0; camera_len];
+        reader.read_exact(&mut camera).map_err(truncated)?;
+        let camera = String::from_utf8(camera).map_err(|_| format("camera is not UTF-8"))?;
+        let camera = toml::from_str(&camera).map_err(|e| format(&e.to_string()))?;
+
+        let width = u32::from_le_bytes(read_array(&mut reader).map_err(truncated)?);
+        let height = u32::from_le_bytes(read_array(&mut reader).map_err(truncated)?);
+        let count = (width as usize)
+            .checked_mul(height as usize)
+            .filter(|count| count.checked_mul(Pixel::ENCODED_LEN) == Some(reader.len()))
+            .ok_or_else(|| format("pixel count does not match the resolution"))?;
+        let mut pixels = Vec::with_capacity(count);
+        for _ in 0..count {
+            pixels.push(Pixel::read(&mut reader).map_err(truncated)?);
+        }
+
+        let film = Film::from_pixels(width, height, pixels)
+            .ok_or_else(|| format("pixel count does not match the resolution"))?;
+
+        Ok(Checkpoint {
+            camera,
+            scene_hash,
+            film,
+        })
+    }
+
+    /// Loads the film saved at `path`, checking it was rendered from the same scene and camera
+    /// settings, sampling and tiling included, so every sample added to it follows the same
+    /// sequence. A run adds more samples by rendering with a per-run sample budget.
+    pub fn resume(path: &Path, camera: &CameraBuilder, scene_hash: u64) -> Result<Film, Error> {
+        let checkpoint = Checkpoint::load(path)?;
+        if checkpoint.scene_hash != scene_hash {
+            return Err(Error::SceneMismatch);
+        }
+
+        if checkpoint.camera != *camera {
+            return Err(Error::CameraMismatch);
+        }
+
+        Ok(checkpoint.film)
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut &[u8]) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(bytes)
+}
@@ -0,0 +1,585 @@
+//! Rendering one image across several processes. A coordinator listens on a socket, sends each
+//! worker that connects the scene, then hands out windows of tiles and merges the radiance the
+//! workers send back into its film. Workers resolve image and mesh paths against the
+//! coordinator's scene directory, so they need to see the same files.
+
+use crate::camera::Camera;
+use crate::film::{Film, Pixel};
+use crate::scene::{self, Scene};
+use crate::tile::Tile;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::fmt::{Display, Formatter};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+const JOB_MAGIC: &[u8; 8] = b"RTJOB001";
+const WORKER_MAGIC: &[u8; 8] = b"RTWRK001";
+
+/// The longest scene or path accepted from a peer, far beyond any real scene file, so a corrupt
+/// length cannot make the reader allocate without bound.
+const MAX_STRING_LEN: u64 = 64 << 20;
+
+const FINISH: u8 = 0;
+const TASK: u8 = 1;
+
+/// Where a coordinator listens: `unix:<path>` for a Unix socket, anything else is a TCP
+/// `host:port`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl FromStr for Address {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        #[cfg(unix)]
+        if let Some(path) = s.strip_prefix("unix:") {
+            return Ok(Address::Unix(PathBuf::from(path)));
+        }
+
+        Ok(Address::Tcp(s.to_string()))
+    }
+}
+
+impl Display for Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Address::Tcp(address) => write!(f, "{address}"),
+            #[cfg(unix)]
+            Address::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(Address, io::Error),
+    Protocol(String),
+    Scene(scene::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(address, e) => write!(f, "{address}: {e}"),
+            Error::Protocol(message) => write!(f, "unexpected message: {message}"),
+            Error::Scene(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn connect(address: &Address) -> io::Result<Stream> {
+        Ok(match address {
+            Address::Tcp(address) => {
+                let stream = TcpStream::connect(address)?;
+                stream.set_nodelay(true)?;
+                Stream::Tcp(stream)
+            }
+            #[cfg(unix)]
+            Address::Unix(path) => Stream::Unix(UnixStream::connect(path)?),
+        })
+    }
+
+    fn try_clone(&self) -> io::Result<Stream> {
+        Ok(match self {
+            Stream::Tcp(stream) => Stream::Tcp(stream.try_clone()?),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Stream::Unix(stream.try_clone()?),
+        })
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    /// Binds `address`, replacing a socket file left behind by an earlier coordinator.
+    fn bind(address: &Address) -> io::Result<Listener> {
+        Ok(match address {
+            Address::Tcp(address) => Listener::Tcp(TcpListener::bind(address)?),
+            #[cfg(unix)]
+            Address::Unix(path) => {
+                use std::os::unix::fs::FileTypeExt;
+
+                if std::fs::metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+                    std::fs::remove_file(path)?;
+                }
+                Listener::Unix(UnixListener::bind(path)?)
+            }
+        })
+    }
+
+    fn accept(&self) -> io::Result<Stream> {
+        Ok(match self {
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nodelay(true)?;
+                Stream::Tcp(stream)
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => Stream::Unix(listener.accept()?.0),
+        })
+    }
+}
+
+/// Everything a worker needs to render the same image as the coordinator.
+#[derive(Clone)]
+struct Job {
+    scene: String,
+    base: String,
+    target: u32,
+}
+
+/// Tiles for a worker to bring to the target, with their pixels' sample counts and luminance
+/// moments so far.
+struct Task {
+    tiles: Vec<Tile>,
+    pixels: Vec<Pixel>,
+}
+
+/// What connection threads tell the coordinating thread.
+enum Event {
+    /// A worker with this many threads is idle; reply with a task, or `None` when there is none.
+    Ready(u32, Sender<Option<Task>>),
+    /// A worker finished `tiles`, splatting `pixels` into `region`.
+    Done {
+        tiles: Vec<Tile>,
+        region: Tile,
+        pixels: Vec<Pixel>,
+    },
+    /// A worker dropped out before finishing these tiles.
+    Failed(Vec<Tile>),
+}
+
+/// Renders `film` up to `target` samples per pixel on whichever workers connect to `address`,
+/// reporting progress through `camera` as results are merged. Returns once every tile is done,
+/// and keeps waiting for workers until then.
+pub fn coordinate(
+    address: &Address,
+    scene: &Scene,
+    base: &Path,
+    camera: &Camera,
+    film: &mut Film,
+    target: u32,
+) -> Result<(), Error> {
+    let mut tracker = camera.tracker(film, camera.tiles(), target);
+    let mut queue: VecDeque<Tile> = camera
+        .tiles()
+        .into_iter()
+        .filter(|tile| !camera.tile_done(film, tile, target))
+        .collect();
+    if queue.is_empty() {
+        return Ok(());
+    }
+
+    let job = Job {
+        scene: scene.to_toml().map_err(Error::Scene)?,
+        base: base
+            .canonicalize()
+            .unwrap_or_else(|_| base.to_path_buf())
+            .to_string_lossy()
+            .into_owned(),
+        target,
+    };
+
+    let listener = Listener::bind(address).map_err(|e| Error::Io(address.clone(), e))?;
+    let (events, received) = mpsc::channel();
+    let accept_address = address.clone();
+    let size = (film.width(), film.height());
+    thread::spawn(move || loop {
+        match listener.accept() {
+            Ok(stream) => {
+                let (job, events) = (job.clone(), events.clone());
+                thread::spawn(move || serve(stream, &job, size, &events));
+            }
+            Err(e) => eprintln!("{accept_address}: {e}"),
+        }
+    });
+
+    let mut outstanding = 0;
+    let mut idle = Vec::new();
+    while !(queue.is_empty() && outstanding == 0) {
+        match received
+            .recv()
+            .expect("the accepting thread never hangs up")
+        {
+            Event::Ready(threads, reply) => idle.push((threads, reply)),
+            Event::Done {
+                tiles,
+                region,
+                pixels,
+            } => {
+                outstanding -= 1;
+                film.merge(region, &pixels, &tiles);
+                for tile in &tiles {
+                    tracker.update(tile, camera.tile_work(film, tile, target));
+                }
+                camera.report(&tracker.progress());
+            }
+            Event::Failed(tiles) => {
+                outstanding -= 1;
+                for tile in tiles.into_iter().rev() {
+                    queue.push_front(tile);
+                }
+            }
+        }
+
+        while !queue.is_empty() {
+            let Some((threads, reply)) = idle.pop() else {
+                break;
+            };
+
+            // As many tiles as the worker's `render_tiles` traces at once.
+            let count = (threads as usize * 4).clamp(1, queue.len());
+            let tiles: Vec<Tile> = queue.drain(..count).collect();
+            let pixels = tiles
+                .iter()
+                .flat_map(Tile::pixels)
+                .map(|(x, y)| *film.pixel(x, y))
+                .collect();
+
+            match reply.send(Some(Task { tiles, pixels })) {
+                Ok(()) => outstanding += 1,
+                Err(mpsc::SendError(task)) => {
+                    let tiles = task.expect("sent a task").tiles;
+                    for tile in tiles.into_iter().rev() {
+                        queue.push_front(tile);
+                    }
+                }
+            }
+        }
+    }
+
+    for (_, reply) in idle {
+        let _ = reply.send(None);
+    }
+
+    Ok(())
+}
+
+/// Tiles handed to a worker, which are reported as failed when this is dropped before they are
+/// done, so the coordinator hands them out again whatever way the connection ends.
+struct Pending<'a> {
+    tiles: Vec<Tile>,
+    events: &'a Sender<Event>,
+}
+
+impl Pending<'_> {
+    /// Reports the tiles done, or failed if the coordinator has stopped listening.
+    fn done(mut self, region: Tile, pixels: Vec<Pixel>) -> bool {
+        let tiles = std::mem::take(&mut self.tiles);
+        self.events
+            .send(Event::Done {
+                tiles,
+                region,
+                pixels,
+            })
+            .is_ok()
+    }
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        if !self.tiles.is_empty() {
+            let _ = self
+                .events
+                .send(Event::Failed(std::mem::take(&mut self.tiles)));
+        }
+    }
+}
+
+/// Whether `region` lies within a film of `size` and covers every tile in `tiles`.
+fn covers(region: &Tile, tiles: &[Tile], (width, height): (u32, u32)) -> bool {
+    region.x0 <= region.x1
+        && region.y0 <= region.y1
+        && region.x1 <= width
+        && region.y1 <= height
+        && tiles.iter().all(|tile| {
+            tile.x0 >= region.x0
+                && tile.y0 >= region.y0
+                && tile.x1 <= region.x1
+                && tile.y1 <= region.y1
+        })
+}
+
+/// Hands one worker the job and then tasks until there are none left, reporting back over
+/// `events`. Film regions the worker sends back are checked against `size` and its tiles before
+/// any pixels are read.
+fn serve(stream: Stream, job: &Job, size: (u32, u32), events: &Sender<Event>) {
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(reader);
+    let mut writer = BufWriter::new(stream);
+
+    let mut hello = || -> io::Result<u32> {
+        writer.write_all(JOB_MAGIC)?;
+        write_string(&mut writer, &job.scene)?;
+        write_string(&mut writer, &job.base)?;
+        writer.write_all(&job.target.to_le_bytes())?;
+        writer.flush()?;
+
+        expect_magic(&mut reader, WORKER_MAGIC)?;
+        read_u32(&mut reader)
+    };
+    let Ok(threads) = hello() else {
+        return;
+    };
+
+    loop {
+        let (reply, task) = mpsc::channel();
+        let task = match events.send(Event::Ready(threads, reply)) {
+            Ok(()) => task.recv().ok().flatten(),
+            Err(_) => None,
+        };
+        let Some(task) = task else {
+            let _ = writer.write_all(&[FINISH]).and_then(|_| writer.flush());
+            return;
+        };
+        let pending = Pending {
+            tiles: task.tiles,
+            events,
+        };
+
+        let mut result = || -> io::Result<(Tile, Vec<Pixel>)> {
+            writer.write_all(&[TASK])?;
+            writer.write_all(&(pending.tiles.len() as u32).to_le_bytes())?;
+            let mut pixels = task.pixels.iter();
+            for tile in &pending.tiles {
+                write_tile(&mut writer, tile)?;
+                for pixel in pixels.by_ref().take(tile.area() as usize) {
+                    writer.write_all(&pixel.samples.to_le_bytes())?;
+                    writer.write_all(&pixel.luminance.to_le_bytes())?;
+                    writer.write_all(&pixel.luminance_squares.to_le_bytes())?;
+                }
+            }
+            writer.flush()?;
+
+            let region = read_tile(&mut reader)?;
+            if !covers(&region, &pending.tiles, size) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{region:?} does not cover the tiles sent or lies outside the film"),
+                ));
+            }
+            let pixels = (0..region.area())
+                .map(|_| Pixel::read(&mut reader))
+                .collect::<io::Result<_>>()?;
+            Ok((region, pixels))
+        };
+
+        match result() {
+            Ok((region, pixels)) => {
+                if !pending.done(region, pixels) {
+                    return;
+                }
+            }
+            Err(e) => {
+                if e.kind() == io::ErrorKind::InvalidData {
+                    eprintln!("a worker sent back {e}");
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// Connects to the coordinator at `address` and renders the tiles it hands out until it has no
+/// more.
+pub fn work(address: &Address) -> Result<(), Error> {
+    let io = |e| Error::Io(address.clone(), e);
+
+    let stream = Stream::connect(address).map_err(io)?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(io)?);
+    let mut writer = BufWriter::new(stream);
+
+    expect_magic(&mut reader, JOB_MAGIC).map_err(io)?;
+    let scene = read_string(&mut reader).map_err(io)?;
+    let base = read_string(&mut reader).map_err(io)?;
+    let target = read_u32(&mut reader).map_err(io)?;
+
+    let scene = Scene::from_toml(&scene).map_err(Error::Scene)?;
+    let (world, lights, camera) = scene.build(Path::new(&base)).map_err(Error::Scene)?;
+
+    writer.write_all(WORKER_MAGIC).map_err(io)?;
+    writer
+        .write_all(&(rayon::current_num_threads() as u32).to_le_bytes())
+        .map_err(io)?;
+    writer.flush().map_err(io)?;
+
+    let (width, height) = camera.resolution();
+    let mut film = camera.film();
+    loop {
+        let mut tag = [0];
+        match reader.read_exact(&mut tag) {
+            // A coordinator that is done may exit before telling every worker.
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            result => result.map_err(io)?,
+        }
+
+        match tag[0] {
+            FINISH => return Ok(()),
+            TASK => {}
+            tag => return Err(Error::Protocol(format!("unknown message {tag}"))),
+        }
+
+        let count = read_u32(&mut reader).map_err(io)?;
+        let mut tiles = Vec::new();
+        for _ in 0..count {
+            let tile = read_tile(&mut reader).map_err(io)?;
+            if tile.x1 > width || tile.y1 > height {
+                return Err(Error::Protocol(format!("{tile:?} is outside the image")));
+            }
+
+            for (x, y) in tile.pixels() {
+                let samples = read_u32(&mut reader).map_err(io)?;
+                let luminance = read_f64(&mut reader).map_err(io)?;
+                let luminance_squares = read_f64(&mut reader).map_err(io)?;
+                film.pixels_mut()[(y * width + x) as usize] = Pixel {
+                    samples,
+                    luminance,
+                    luminance_squares,
+                    ..Pixel::default()
+                };
+            }
+            tiles.push(tile);
+        }
+
+        camera.render_tiles(&world, &lights, &mut film, target, &tiles, |_| {});
+
+        let region = camera.footprint(&tiles);
+        write_tile(&mut writer, &region).map_err(io)?;
+        for (x, y) in region.pixels() {
+            film.pixel(x, y).write(&mut writer).map_err(io)?;
+        }
+        writer.flush().map_err(io)?;
+
+        for (x, y) in region.pixels() {
+            film.pixels_mut()[(y * width + x) as usize] = Pixel::default();
+        }
+    }
+}
+
+fn expect_magic(reader: &mut impl Read, magic: &[u8; 8]) -> io::Result<()> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    if &bytes != magic {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the other side is not a compatible renderer",
+        ));
+    }
+
+    Ok(())
+}
+
+fn write_string(writer: &mut impl Write, text: &str) -> io::Result<()> {
+    writer.write_all(&(text.len() as u64).to_le_bytes())?;
+    writer.write_all(text.as_bytes())
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let mut len = [0; 8];
+    reader.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    if len > MAX_STRING_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("string of {len} bytes is longer than {MAX_STRING_LEN}"),
+        ));
+    }
+    let mut bytes = vec![0; len as usize];
+    reader.read_exact(&mut bytes)?;
+
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_tile(writer: &mut impl Write, tile: &Tile) -> io::Result<()> {
+    for value in [tile.x0, tile.y0, tile.x1, tile.y1] {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn read_tile(reader: &mut impl Read) -> io::Result<Tile> {
+    let tile = Tile {
+        x0: read_u32(reader)?,
+        y0: read_u32(reader)?,
+        x1: read_u32(reader)?,
+        y1: read_u32(reader)?,
+    };
+    if tile.x0 > tile.x1 || tile.y0 > tile.y1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{tile:?} is not a valid window"),
+        ));
+    }
+
+    Ok(tile)
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f64(reader: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(f64::from_le_bytes(bytes))
+}
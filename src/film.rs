@@ -0,0 +1,314 @@
+use crate::filter::Filter;
+use crate::tile::Tile;
+use crate::tonemap::{luminance, DisplayTransform};
+use crate::vec3::Vec3;
+use image::error::{ParameterError, ParameterErrorKind};
+use image::{ImageError, ImageResult, Rgb, Rgb32FImage, RgbImage};
+use rayon::prelude::*;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// The radiance gathered for one pixel so far. `sum` and `weight` include the samples of
+/// neighbouring pixels the filter reaches, while `samples` and the luminance moments only cover
+/// those taken in this one.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Pixel {
+    pub sum: Vec3,
+    pub weight: f64,
+    pub samples: u32,
+    pub luminance: f64,
+    pub luminance_squares: f64,
+}
+
+impl Pixel {
+    pub fn estimate(&self) -> Vec3 {
+        if self.weight == 0.0 {
+            Vec3::scalar(0.0)
+        } else {
+            self.sum / self.weight
+        }
+    }
+
+    /// The standard error of the mean luminance of this pixel's samples relative to that mean,
+    /// which becomes small as the pixel converges. Means below 0.001 count as 0.001, so black
+    /// pixels do not need an exact zero to converge.
+    pub fn relative_error(&self) -> f64 {
+        if self.samples < 2 {
+            return f64::INFINITY;
+        }
+
+        let n = self.samples as f64;
+        let mean = self.luminance / n;
+        let variance = ((self.luminance_squares / n - mean * mean) * n / (n - 1.0)).max(0.0);
+
+        (variance / n).sqrt() / mean.max(1e-3)
+    }
+
+    /// Writes the pixel as little-endian numbers, the layout checkpoints and workers share.
+    /// The bytes [`Pixel::write`] produces.
+    pub(crate) const ENCODED_LEN: usize = 52;
+
+    pub(crate) fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        for channel in self.sum.0 {
+            out.write_all(&channel.to_le_bytes())?;
+        }
+        out.write_all(&self.weight.to_le_bytes())?;
+        out.write_all(&self.samples.to_le_bytes())?;
+        out.write_all(&self.luminance.to_le_bytes())?;
+        out.write_all(&self.luminance_squares.to_le_bytes())
+    }
+
+    pub(crate) fn read(input: &mut impl Read) -> io::Result<Pixel> {
+        fn array<const N: usize>(input: &mut impl Read) -> io::Result<[u8; N]> {
+            let mut bytes = [0; N];
+            input.read_exact(&mut bytes)?;
+            Ok(bytes)
+        }
+
+        let mut sum = Vec3::scalar(0.0);
+        for channel in sum.0.iter_mut() {
+            *channel = f64::from_le_bytes(array(input)?);
+        }
+
+        Ok(Pixel {
+            sum,
+            weight: f64::from_le_bytes(array(input)?),
+            samples: u32::from_le_bytes(array(input)?),
+            luminance: f64::from_le_bytes(array(input)?),
+            luminance_squares: f64::from_le_bytes(array(input)?),
+        })
+    }
+}
+
+/// The radiance carried by one camera sample, and where in its pixel the sample was taken, with
+/// both coordinates in `[0, 1)`.
+#[derive(Debug, Copy, Clone)]
+pub struct FilmSample {
+    pub offset: [f64; 2],
+    pub radiance: Vec3,
+}
+
+/// A floating point accumulation buffer that rendering passes add samples to, so an image can be
+/// produced at any point during a render.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Film {
+    width: u32,
+    height: u32,
+    pixels: Vec<Pixel>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32) -> Film {
+        Film {
+            width,
+            height,
+            pixels: vec![Pixel::default(); (width * height) as usize],
+        }
+    }
+
+    /// Rebuilds a film from row-major pixels, or `None` if there are not `width * height` of them.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<Pixel>) -> Option<Film> {
+        if pixels.len() != (width as usize) * (height as usize) {
+            return None;
+        }
+
+        Some(Film {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> &Pixel {
+        &self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Pixels in row-major order.
+    pub fn pixels(&self) -> &[Pixel] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Pixel] {
+        &mut self.pixels
+    }
+
+    /// Adds the samples taken in the pixels of `tiles`, given in row-major order over the whole
+    /// film, to every pixel `filter` reaches. Each pixel gathers from its neighbours in a fixed
+    /// order, so the result does not depend on how the work is spread across threads.
+    pub fn splat(&mut self, samples: &[Vec<FilmSample>], tiles: &[Tile], filter: &dyn Filter) {
+        let (width, height) = (self.width as i64, self.height as i64);
+        let radius = filter.radius();
+        let reach = filter.reach() as i64;
+
+        let mut reached = vec![false; self.pixels.len()];
+        for tile in tiles {
+            for y in (tile.y0 as i64 - reach).max(0)..(tile.y1 as i64 + reach).min(height) {
+                for x in (tile.x0 as i64 - reach).max(0)..(tile.x1 as i64 + reach).min(width) {
+                    reached[(y * width + x) as usize] = true;
+                }
+            }
+        }
+
+        self.pixels
+            .par_iter_mut()
+            .enumerate()
+            .filter(|(i, _)| reached[*i])
+            .for_each(|(i, pixel)| {
+                let (x, y) = (i as i64 % width, i as i64 / width);
+                for sy in (y - reach).max(0)..=(y + reach).min(height - 1) {
+                    for sx in (x - reach).max(0)..=(x + reach).min(width - 1) {
+                        for sample in &samples[(sy * width + sx) as usize] {
+                            let dx = (sx - x) as f64 + sample.offset[0] - 0.5;
+                            let dy = (sy - y) as f64 + sample.offset[1] - 0.5;
+                            if dx.abs() > radius || dy.abs() > radius {
+                                continue;
+                            }
+
+                            let weight = filter.evaluate(dx, dy);
+                            pixel.sum += weight * sample.radiance;
+                            pixel.weight += weight;
+                        }
+                    }
+                }
+
+                for sample in &samples[i] {
+                    let luminance = luminance(sample.radiance);
+                    pixel.luminance += luminance;
+                    pixel.luminance_squares += luminance * luminance;
+                }
+                pixel.samples += samples[i].len() as u32;
+            });
+    }
+
+    /// Adds `pixels`, the radiance splatted into `region` by a render elsewhere, and takes the
+    /// sample counts and luminance moments of the pixels in `tiles` from them, since those pixels
+    /// were sampled there.
+    pub fn merge(&mut self, region: Tile, pixels: &[Pixel], tiles: &[Tile]) {
+        for ((x, y), merged) in region.pixels().zip(pixels) {
+            let i = (y * self.width + x) as usize;
+            self.pixels[i].sum += merged.sum;
+            self.pixels[i].weight += merged.weight;
+        }
+
+        for (x, y) in tiles.iter().flat_map(Tile::pixels) {
+            let merged = &pixels[((y - region.y0) * region.width() + x - region.x0) as usize];
+            let pixel = &mut self.pixels[(y * self.width + x) as usize];
+            pixel.samples = merged.samples;
+            pixel.luminance = merged.luminance;
+            pixel.luminance_squares = merged.luminance_squares;
+        }
+    }
+
+    /// The fewest samples any pixel has received.
+    pub fn samples(&self) -> u32 {
+        self.pixels.iter().map(|p| p.samples).min().unwrap_or(0)
+    }
+
+    /// The most samples any pixel has received, which with adaptive sampling is what the pixels
+    /// still being refined are at.
+    pub fn max_samples(&self) -> u32 {
+        self.pixels.iter().map(|p| p.samples).max().unwrap_or(0)
+    }
+
+    /// How many samples each pixel took, from black for none to white for the most any took,
+    /// through red and yellow.
+    pub fn sample_heatmap(&self) -> RgbImage {
+        let max = self.max_samples().max(1) as f64;
+
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let t = 3.0 * self.pixel(x, y).samples as f64 / max;
+            Rgb([t, t - 1.0, t - 2.0].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+        })
+    }
+
+    /// The pixels of `window` as a film of their own.
+    pub fn cropped(&self, window: Tile) -> Film {
+        Film {
+            width: window.width(),
+            height: window.height(),
+            pixels: window.pixels().map(|(x, y)| *self.pixel(x, y)).collect(),
+        }
+    }
+
+    pub fn to_image(&self, display: &DisplayTransform) -> RgbImage {
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            display.apply(self.pixel(x, y).estimate())
+        })
+    }
+
+    /// The current estimate as linear radiance, without clamping anything above 1.0.
+    pub fn to_linear_image(&self) -> Rgb32FImage {
+        Rgb32FImage::from_fn(self.width, self.height, |x, y| {
+            Rgb(self.pixel(x, y).estimate().0.map(|c| c as f32))
+        })
+    }
+
+    /// Saves the current estimate, keeping untouched linear radiance for `.exr` and `.hdr` files
+    /// and passing it through `display` for everything else.
+    pub fn save(&self, path: &Path, display: &DisplayTransform) -> ImageResult<()> {
+        if is_linear(path) {
+            self.to_linear_image().save(path)
+        } else {
+            self.to_image(display).save(path)
+        }
+    }
+
+    /// Saves the image at `background` to `path` with the pixels of `window` replaced by the
+    /// current estimate. `background` must be the size of the film, and linear exactly when
+    /// `path` is, since its pixels are copied without changing their encoding.
+    pub fn save_pasted(
+        &self,
+        window: Tile,
+        background: &Path,
+        path: &Path,
+        display: &DisplayTransform,
+    ) -> ImageResult<()> {
+        if is_linear(background) != is_linear(path) {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::Generic(
+                    "background and output must both be linear or both be display images"
+                        .to_string(),
+                ),
+            )));
+        }
+
+        let background = image::open(background)?;
+        if (background.width(), background.height()) != (self.width, self.height) {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+
+        if is_linear(path) {
+            let mut image = background.into_rgb32f();
+            for (x, y) in window.pixels() {
+                image.put_pixel(x, y, Rgb(self.pixel(x, y).estimate().0.map(|c| c as f32)));
+            }
+            image.save(path)
+        } else {
+            let mut image = background.into_rgb8();
+            for (x, y) in window.pixels() {
+                image.put_pixel(x, y, display.apply(self.pixel(x, y).estimate()));
+            }
+            image.save(path)
+        }
+    }
+}
+
+/// Whether images saved to `path` keep linear radiance rather than display values.
+pub fn is_linear(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+
+    extension.eq_ignore_ascii_case("exr") || extension.eq_ignore_ascii_case("hdr")
+}
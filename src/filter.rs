@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Weighs a sample by its offset from a pixel's centre, in pixels. Samples reach every pixel
+/// within `radius` of them, which smooths edges at the cost of some sharpness.
+pub trait Filter: Sync {
+    fn radius(&self) -> f64;
+
+    fn evaluate(&self, x: f64, y: f64) -> f64;
+
+    /// How many pixels away from the one it was taken in a sample can land.
+    fn reach(&self) -> u32 {
+        ((self.radius() + 0.5).ceil() as u32).saturating_sub(1)
+    }
+}
+
+/// Which [`Filter`] a camera reconstructs pixels with.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FilterKind {
+    #[default]
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    Lanczos,
+}
+
+impl FilterKind {
+    pub fn build(self) -> Box<dyn Filter> {
+        match self {
+            FilterKind::Box => Box::new(BoxFilter { radius: 0.5 }),
+            FilterKind::Tent => Box::new(Tent { radius: 1.0 }),
+            FilterKind::Gaussian => Box::new(Gaussian {
+                radius: 1.5,
+                sigma: 0.5,
+            }),
+            FilterKind::Mitchell => Box::new(Mitchell {
+                radius: 2.0,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            }),
+            FilterKind::Lanczos => Box::new(Lanczos {
+                radius: 3.0,
+                tau: 3.0,
+            }),
+        }
+    }
+}
+
+/// Averages the samples inside a square, which with a radius of half a pixel is what the
+/// renderer always did.
+#[derive(Debug, Copy, Clone)]
+pub struct BoxFilter {
+    pub radius: f64,
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        if x.abs() <= self.radius && y.abs() <= self.radius {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Tent {
+    pub radius: f64,
+}
+
+impl Filter for Tent {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        (self.radius - x.abs()).max(0.0) * (self.radius - y.abs()).max(0.0)
+    }
+}
+
+/// A Gaussian with standard deviation `sigma`, shifted down to reach zero at `radius`.
+#[derive(Debug, Copy, Clone)]
+pub struct Gaussian {
+    pub radius: f64,
+    pub sigma: f64,
+}
+
+impl Gaussian {
+    fn gaussian(&self, x: f64) -> f64 {
+        let edge = (-self.radius.powi(2) / (2.0 * self.sigma.powi(2))).exp();
+
+        ((-x.powi(2) / (2.0 * self.sigma.powi(2))).exp() - edge).max(0.0)
+    }
+}
+
+impl Filter for Gaussian {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.gaussian(x) * self.gaussian(y)
+    }
+}
+
+/// The Mitchell–Netravali cubic. Its slightly negative lobes sharpen edges; `b = c = 1/3` is the
+/// compromise between blurring and ringing the authors recommend.
+#[derive(Debug, Copy, Clone)]
+pub struct Mitchell {
+    pub radius: f64,
+    pub b: f64,
+    pub c: f64,
+}
+
+impl Mitchell {
+    fn mitchell(&self, x: f64) -> f64 {
+        let (b, c) = (self.b, self.c);
+        let x = (2.0 * x / self.radius).abs();
+
+        if x <= 1.0 {
+            ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+                + (6.0 - 2.0 * b))
+                / 6.0
+        } else if x <= 2.0 {
+            ((-b - 6.0 * c) * x.powi(3)
+                + (6.0 * b + 30.0 * c) * x.powi(2)
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c))
+                / 6.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Filter for Mitchell {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.mitchell(x) * self.mitchell(y)
+    }
+}
+
+/// A sinc windowed by a wider sinc that stretches `tau` pixels, the sharpest of the filters.
+#[derive(Debug, Copy, Clone)]
+pub struct Lanczos {
+    pub radius: f64,
+    pub tau: f64,
+}
+
+impl Lanczos {
+    fn windowed_sinc(&self, x: f64) -> f64 {
+        if x.abs() > self.radius {
+            0.0
+        } else {
+            sinc(x) * sinc(x / self.tau)
+        }
+    }
+}
+
+impl Filter for Lanczos {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.windowed_sinc(x) * self.windowed_sinc(y)
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
@@ -1,10 +1,15 @@
 use crate::aabb::Aabb;
 use crate::interval::Interval;
 use crate::material::Material;
+use crate::matrix::{Matrix4, Quaternion};
+use crate::onb::Onb;
 use crate::ray::Ray;
+use crate::stats::{self, Counter};
 use crate::vec3::Vec3;
+use rand::rngs::SmallRng;
 use rand::Rng;
 use std::f64::consts::PI;
+use std::sync::Arc;
 
 pub struct HitRecord<'m> {
     pub point: Vec3,
@@ -16,19 +21,71 @@ pub struct HitRecord<'m> {
     pub v: f64,
 }
 
-pub trait Hittable: Sync {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord>;
+pub trait Hittable: Send + Sync {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rng: &mut SmallRng) -> Option<HitRecord<'_>>;
 
     fn bounding_box(&self) -> Aabb;
+
+    /// Density, over solid angle seen from the ray's origin, of the directions returned by
+    /// [`Hittable::random`] at the ray's time. Objects that cannot be sampled as lights return
+    /// zero.
+    fn pdf_value(&self, _ray: &Ray) -> f64 {
+        0.0
+    }
+
+    /// A direction from `origin` towards a point on this object where it is at `time`, picked by
+    /// the uniform values `u`.
+    fn random(&self, _origin: Vec3, _time: f64, _u: [f64; 2]) -> Vec3 {
+        Vec3::x(1.0)
+    }
+}
+
+impl Hittable for Box<dyn Hittable> {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rng: &mut SmallRng) -> Option<HitRecord<'_>> {
+        self.as_ref().hit(ray, ray_t, rng)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.as_ref().bounding_box()
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        self.as_ref().pdf_value(ray)
+    }
+
+    fn random(&self, origin: Vec3, time: f64, u: [f64; 2]) -> Vec3 {
+        self.as_ref().random(origin, time, u)
+    }
+}
+
+impl<H> Hittable for Arc<H>
+where
+    H: Hittable + ?Sized,
+{
+    fn hit(&self, ray: &Ray, ray_t: Interval, rng: &mut SmallRng) -> Option<HitRecord<'_>> {
+        self.as_ref().hit(ray, ray_t, rng)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.as_ref().bounding_box()
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        self.as_ref().pdf_value(ray)
+    }
+
+    fn random(&self, origin: Vec3, time: f64, u: [f64; 2]) -> Vec3 {
+        self.as_ref().random(origin, time, u)
+    }
 }
 
 impl Hittable for Vec<Box<dyn Hittable>> {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rng: &mut SmallRng) -> Option<HitRecord<'_>> {
         let mut hit = None;
         let mut closest_so_far = ray_t.max;
 
         for thing in self.iter() {
-            if let Some(temp) = thing.hit(ray, Interval::new(ray_t.min, closest_so_far)) {
+            if let Some(temp) = thing.hit(ray, Interval::new(ray_t.min, closest_so_far), rng) {
                 closest_so_far = temp.t;
                 hit = Some(temp);
             }
@@ -52,6 +109,27 @@ impl Hittable for Vec<Box<dyn Hittable>> {
 
         bounds
     }
+
+    /// Picks each object with equal probability, so a list of lights can be sampled as a whole.
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+
+        let sum: f64 = self.iter().map(|thing| thing.pdf_value(ray)).sum();
+        sum / self.len() as f64
+    }
+
+    fn random(&self, origin: Vec3, time: f64, u: [f64; 2]) -> Vec3 {
+        if self.is_empty() {
+            return Vec3::x(1.0);
+        }
+
+        // The first value picks the object and what is left of it is reused within the object.
+        let scaled = u[0] * self.len() as f64;
+        let index = (scaled as usize).min(self.len() - 1);
+        self[index].random(origin, time, [scaled - index as f64, u[1]])
+    }
 }
 
 pub enum Center {
@@ -105,11 +183,13 @@ impl<M> Sphere<M> {
     }
 }
 
-impl<M> Hittable for Sphere<M>
+impl<M> Sphere<M>
 where
     M: Material,
 {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+    /// Intersection needs no randomness, which lets [`Hittable::pdf_value`] use it without a
+    /// generator. Only tests made from `hit` are counted, so light sampling leaves the stats alone.
+    fn intersect(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
         let center = self.center(ray.time);
         let oc = center - ray.origin;
         let a = ray.direction.length_squared();
@@ -152,10 +232,55 @@ where
             v,
         })
     }
+}
+
+impl<M> Hittable for Sphere<M>
+where
+    M: Material + Send,
+{
+    fn hit(&self, ray: &Ray, ray_t: Interval, _rng: &mut SmallRng) -> Option<HitRecord<'_>> {
+        stats::count(Counter::SphereTests);
+        self.intersect(ray, ray_t)
+    }
 
     fn bounding_box(&self) -> Aabb {
         self.bounds.clone()
     }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        if self
+            .intersect(ray, Interval::new(0.001, f64::MAX))
+            .is_none()
+        {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center(ray.time) - ray.origin).length_squared();
+        if distance_squared <= self.radius.powi(2) {
+            return 0.0;
+        }
+
+        let cos_theta_max = (1.0 - self.radius.powi(2) / distance_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+        1.0 / solid_angle
+    }
+
+    /// Samples the cone of directions subtended by the sphere, which is uniform in solid angle.
+    fn random(&self, origin: Vec3, time: f64, u: [f64; 2]) -> Vec3 {
+        let direction = self.center(time) - origin;
+        let distance_squared = direction.length_squared();
+        let cos_theta_max = (1.0 - self.radius.powi(2) / distance_squared)
+            .max(0.0)
+            .sqrt();
+
+        let [r1, r2] = u;
+        let z = 1.0 + r2 * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * r1;
+        let sin_theta = (1.0 - z * z).sqrt();
+
+        Onb::new(direction).transform(Vec3([phi.cos() * sin_theta, phi.sin() * sin_theta, z]))
+    }
 }
 
 pub struct Quad<M> {
@@ -167,6 +292,7 @@ pub struct Quad<M> {
     bounds: Aabb,
     normal: Vec3,
     d: f64,
+    area: f64,
 }
 
 impl<M> Quad<M> {
@@ -180,6 +306,7 @@ impl<M> Quad<M> {
         let d = normal.dot(q);
 
         let w = n / n.dot(n);
+        let area = n.length();
 
         Quad {
             q,
@@ -190,6 +317,7 @@ impl<M> Quad<M> {
             bounds,
             normal,
             d,
+            area,
         }
     }
 
@@ -200,11 +328,11 @@ impl<M> Quad<M> {
     }
 }
 
-impl<M> Hittable for Quad<M>
+impl<M> Quad<M>
 where
     M: Material,
 {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+    fn intersect(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
         let denom = self.normal.dot(ray.direction);
 
         if denom.abs() < 1e-8 {
@@ -243,6 +371,141 @@ where
             v,
         })
     }
+}
+
+impl<M> Hittable for Quad<M>
+where
+    M: Material + Send,
+{
+    fn hit(&self, ray: &Ray, ray_t: Interval, _rng: &mut SmallRng) -> Option<HitRecord<'_>> {
+        stats::count(Counter::QuadTests);
+        self.intersect(ray, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounds.clone()
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        let Some(hit) = self.intersect(ray, Interval::new(0.001, f64::MAX)) else {
+            return 0.0;
+        };
+
+        let direction = ray.direction;
+        let distance_squared = hit.t.powi(2) * direction.length_squared();
+        let cosine = (direction.dot(self.normal) / direction.length()).abs();
+
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Vec3, _time: f64, u: [f64; 2]) -> Vec3 {
+        let point = self.q + u[0] * self.u + u[1] * self.v;
+
+        point - origin
+    }
+}
+
+pub struct Triangle<M> {
+    vertices: [Vec3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+    material: M,
+    bounds: Aabb,
+    normal: Vec3,
+}
+
+impl<M> Triangle<M> {
+    pub fn new(a: Vec3, b: Vec3, c: Vec3, material: M) -> Triangle<M> {
+        let bounds = Aabb::from_bounds(Aabb::from_points(a, b), Aabb::from_points(c, c));
+        let normal = (b - a).cross(c - a).unit();
+
+        Triangle {
+            vertices: [a, b, c],
+            normals: None,
+            uvs: None,
+            material,
+            bounds,
+            normal,
+        }
+    }
+
+    /// Interpolates these per-vertex normals for shading instead of using the flat face normal.
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Triangle<M> {
+        self.normals = Some(normals.map(|n| n.unit()));
+        self
+    }
+
+    /// Interpolates these per-vertex texture coordinates instead of reporting barycentrics.
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Triangle<M> {
+        self.uvs = Some(uvs);
+        self
+    }
+}
+
+impl<M> Hittable for Triangle<M>
+where
+    M: Material + Send,
+{
+    fn hit(&self, ray: &Ray, ray_t: Interval, _rng: &mut SmallRng) -> Option<HitRecord<'_>> {
+        stats::count(Counter::TriangleTests);
+        let [a, b, c] = self.vertices;
+        let edge1 = b - a;
+        let edge2 = c - a;
+
+        let p = ray.direction.cross(edge2);
+        let det = edge1.dot(p);
+        if det.abs() < 1e-8 {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let s = ray.origin - a;
+        let beta = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        let q = s.cross(edge1);
+        let gamma = ray.direction.dot(q) * inv_det;
+        if gamma < 0.0 || beta + gamma > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(q) * inv_det;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let alpha = 1.0 - beta - gamma;
+        let front_face = ray.direction.dot(self.normal) < 0.0;
+        let outward_normal = match self.normals {
+            Some([na, nb, nc]) => (alpha * na + beta * nb + gamma * nc).unit(),
+            None => self.normal,
+        };
+        let normal = if front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+
+        let (u, v) = match self.uvs {
+            Some([ta, tb, tc]) => (
+                alpha * ta.0 + beta * tb.0 + gamma * tc.0,
+                alpha * ta.1 + beta * tb.1 + gamma * tc.1,
+            ),
+            None => (beta, gamma),
+        };
+
+        Some(HitRecord {
+            point: ray.at(t),
+            normal,
+            t,
+            front_face,
+            material: &self.material,
+            u,
+            v,
+        })
+    }
 
     fn bounding_box(&self) -> Aabb {
         self.bounds.clone()
@@ -252,7 +515,7 @@ where
 pub fn make_box(
     a: Vec3,
     b: Vec3,
-    material: impl Material + Clone + 'static,
+    material: impl Material + Clone + Send + 'static,
 ) -> Vec<Box<dyn Hittable>> {
     let min = Vec3([a[0].min(b[0]), a[1].min(b[1]), a[2].min(b[2])]);
     let max = Vec3([a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])]);
@@ -301,39 +564,53 @@ pub fn make_box(
     ]
 }
 
-pub struct Translate<H> {
+/// Places an object with an affine transform, mapping each ray into the object's own space and
+/// the hit back out again.
+pub struct Transform<H> {
     object: H,
-    offset: Vec3,
+    matrix: Matrix4,
+    inverse: Matrix4,
+    normal: Matrix4,
     bounds: Aabb,
 }
 
-impl<H> Translate<H>
+impl<H> Transform<H>
 where
     H: Hittable,
 {
-    pub fn new(object: H, offset: Vec3) -> Translate<H> {
-        let bounds = object.bounding_box() + offset;
-        Translate {
-            object,
-            offset,
-            bounds,
+    /// Places `object` with `matrix`, or returns `None` if the matrix is not an invertible affine
+    /// transform.
+    pub fn new(object: H, matrix: Matrix4) -> Option<Transform<H>> {
+        if !matrix.is_affine() {
+            return None;
         }
+        let inverse = matrix.inverse()?;
+
+        Some(Transform {
+            bounds: matrix.transform_bounds(&object.bounding_box()),
+            object,
+            matrix,
+            inverse,
+            normal: inverse.transpose(),
+        })
     }
 }
 
-impl<H> Hittable for Translate<H>
+impl<H> Hittable for Transform<H>
 where
     H: Hittable,
 {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
-        let offset_r = Ray {
-            origin: ray.origin - self.offset,
-            direction: ray.direction,
+    fn hit(&self, ray: &Ray, ray_t: Interval, rng: &mut SmallRng) -> Option<HitRecord<'_>> {
+        // The direction is not renormalised, so distances along the ray stay the same.
+        let local = Ray {
+            origin: self.inverse.transform_point(ray.origin),
+            direction: self.inverse.transform_vector(ray.direction),
             time: ray.time,
         };
 
-        self.object.hit(&offset_r, ray_t).map(|mut hit| {
-            hit.point += self.offset;
+        self.object.hit(&local, ray_t, rng).map(|mut hit| {
+            hit.point = self.matrix.transform_point(hit.point);
+            hit.normal = self.normal.transform_vector(hit.normal).unit();
             hit
         })
     }
@@ -343,93 +620,152 @@ where
     }
 }
 
-pub struct RotateY<H> {
+/// Poses sampled along each stretch between keyframes when bounding an animated transform.
+const BOUNDS_STEPS: usize = 16;
+
+/// Where an animated object is at one moment of the shutter interval: scaled, then rotated about
+/// its origin, then moved by `offset`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Keyframe {
+    pub time: f64,
+    pub offset: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    /// Whether every part is finite, which rules out rotations about a zero axis.
+    fn is_finite(&self) -> bool {
+        let finite = |v: Vec3| v.0.iter().all(|c| c.is_finite());
+        self.time.is_finite()
+            && finite(self.offset)
+            && self.rotation.w.is_finite()
+            && finite(self.rotation.v)
+            && finite(self.scale)
+    }
+
+    /// The pose a fraction `t` of the way from this keyframe to `next`.
+    fn lerp(&self, next: &Keyframe, t: f64) -> Keyframe {
+        Keyframe {
+            time: self.time + t * (next.time - self.time),
+            offset: self.offset + t * (next.offset - self.offset),
+            rotation: self.rotation.slerp(next.rotation, t),
+            scale: self.scale + t * (next.scale - self.scale),
+        }
+    }
+
+    /// The matrix, its inverse and its normal matrix, built from the parts rather than by
+    /// inverting, since this runs for every ray.
+    fn matrices(&self) -> (Matrix4, Matrix4, Matrix4) {
+        let rotation = self.rotation.to_matrix();
+        let inverse_scale = Matrix4::scaling(Vec3(self.scale.0.map(|s| 1.0 / s)));
+
+        (
+            Matrix4::translation(self.offset) * rotation * Matrix4::scaling(self.scale),
+            inverse_scale * rotation.transpose() * Matrix4::translation(-self.offset),
+            rotation * inverse_scale,
+        )
+    }
+}
+
+/// Moves an object through keyframed poses over the shutter interval, for motion blur on anything
+/// that can be hit. Translation and scale are interpolated linearly and rotation spherically, the
+/// shorter way round, so a spin needs a keyframe at least every half turn. Before the first
+/// keyframe and after the last the object holds still.
+pub struct AnimatedTransform<H> {
     object: H,
-    sin_theta: f64,
-    cos_theta: f64,
+    keyframes: Vec<Keyframe>,
     bounds: Aabb,
 }
 
-impl<H> RotateY<H>
+impl<H> AnimatedTransform<H>
 where
     H: Hittable,
 {
-    pub fn new(object: H, angle: f64) -> RotateY<H> {
-        let radians = angle.to_radians();
-        let sin_theta = radians.sin();
-        let cos_theta = radians.cos();
-
-        let original_bounds = object.bounding_box();
-
-        let mut min = Vec3::scalar(f64::MAX);
-        let mut max = Vec3::scalar(f64::MIN);
-
-        for i in 0..2 {
-            for j in 0..2 {
-                for k in 0..2 {
-                    let x =
-                        i as f64 * original_bounds.x.max + (1.0 - i as f64) * original_bounds.x.min;
-                    let y =
-                        j as f64 * original_bounds.y.max + (1.0 - j as f64) * original_bounds.y.min;
-                    let z =
-                        k as f64 * original_bounds.z.max + (1.0 - k as f64) * original_bounds.z.min;
-
-                    let newx = cos_theta * x + sin_theta * z;
-                    let newz = -sin_theta * x + cos_theta * z;
-
-                    let tester = Vec3([newx, y, newz]);
-
-                    for c in 0..3 {
-                        min.0[c] = min[c].min(tester[c]);
-                        max.0[c] = max[c].max(tester[c]);
-                    }
-                }
-            }
+    /// Animates `object` through `keyframes`, or returns `None` if there are none, any part of
+    /// one is not finite, their times do not increase, or a scale is zero or changes sign between
+    /// keyframes.
+    pub fn new(object: H, keyframes: Vec<Keyframe>) -> Option<AnimatedTransform<H>> {
+        let first = keyframes.first()?;
+        let valid = keyframes
+            .iter()
+            .all(|keyframe| keyframe.is_finite() && !keyframe.scale.0.contains(&0.0))
+            && keyframes.windows(2).all(|pair| {
+                pair[0].time < pair[1].time
+                    && (0..3).all(|i| pair[0].scale[i].signum() == pair[1].scale[i].signum())
+            });
+        if !valid {
+            return None;
         }
 
-        let bounds = Aabb::from_points(min, max);
+        let local = object.bounding_box();
+        let mut bounds = first.matrices().0.transform_bounds(&local);
+        // Poses between the sampled ones stray from the straight line joining them by at most
+        // an eighth of the step squared times the second derivative of the motion.
+        let radius = Vec3([
+            local.x.min.abs().max(local.x.max.abs()),
+            local.y.min.abs().max(local.y.max.abs()),
+            local.z.min.abs().max(local.z.max.abs()),
+        ])
+        .length();
+        let largest = |v: Vec3| v.0.iter().fold(0.0_f64, |m, c| m.max(c.abs()));
+        let mut padding = 0.0_f64;
+        for pair in keyframes.windows(2) {
+            let (from, to) = (&pair[0], &pair[1]);
+            for step in 1..=BOUNDS_STEPS {
+                let pose = from.lerp(to, step as f64 / BOUNDS_STEPS as f64);
+                bounds = Aabb::from_bounds(bounds, pose.matrices().0.transform_bounds(&local));
+            }
+
+            let step_angle = from.rotation.angle_to(to.rotation) / BOUNDS_STEPS as f64;
+            let scale = largest(from.scale).max(largest(to.scale));
+            let scale_step = largest(to.scale - from.scale) / BOUNDS_STEPS as f64;
+            padding = padding
+                .max(radius * (step_angle.powi(2) * scale + 2.0 * step_angle * scale_step) / 8.0);
+        }
 
-        RotateY {
+        Some(AnimatedTransform {
+            bounds: Aabb::new(
+                bounds.x.expand(2.0 * padding),
+                bounds.y.expand(2.0 * padding),
+                bounds.z.expand(2.0 * padding),
+            ),
             object,
-            sin_theta,
-            cos_theta,
-            bounds,
+            keyframes,
+        })
+    }
+
+    fn pose(&self, time: f64) -> Keyframe {
+        let next = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time);
+        if next == 0 {
+            return self.keyframes[0];
         }
+        let Some(to) = self.keyframes.get(next) else {
+            return self.keyframes[next - 1];
+        };
+
+        let from = &self.keyframes[next - 1];
+        from.lerp(to, (time - from.time) / (to.time - from.time))
     }
 }
 
-impl<H> Hittable for RotateY<H>
+impl<H> Hittable for AnimatedTransform<H>
 where
     H: Hittable,
 {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
-        let mut origin = ray.origin;
-        let mut direction = ray.direction;
-
-        origin.0[0] = self.cos_theta * ray.origin[0] - self.sin_theta * ray.origin[2];
-        origin.0[2] = self.sin_theta * ray.origin[0] + self.cos_theta * ray.origin[2];
-
-        direction.0[0] = self.cos_theta * ray.direction[0] - self.sin_theta * ray.direction[2];
-        direction.0[2] = self.sin_theta * ray.direction[0] + self.cos_theta * ray.direction[2];
-
-        let rotated = Ray {
-            origin,
-            direction,
+    fn hit(&self, ray: &Ray, ray_t: Interval, rng: &mut SmallRng) -> Option<HitRecord<'_>> {
+        let (matrix, inverse, normal) = self.pose(ray.time).matrices();
+        let local = Ray {
+            origin: inverse.transform_point(ray.origin),
+            direction: inverse.transform_vector(ray.direction),
             time: ray.time,
         };
 
-        self.object.hit(&rotated, ray_t).map(|mut hit| {
-            let mut p = hit.point;
-            p.0[0] = self.cos_theta * hit.point[0] + self.sin_theta * hit.point[2];
-            p.0[2] = -self.sin_theta * hit.point[0] + self.cos_theta * hit.point[2];
-
-            let mut normal = hit.normal;
-            normal.0[0] = self.cos_theta * hit.normal[0] + self.sin_theta * hit.normal[2];
-            normal.0[0] = -self.sin_theta * hit.normal[0] + self.cos_theta * hit.normal[2];
-
-            hit.point = p;
-            hit.normal = normal;
-
+        self.object.hit(&local, ray_t, rng).map(|mut hit| {
+            hit.point = matrix.transform_point(hit.point);
+            hit.normal = normal.transform_vector(hit.normal).unit();
             hit
         })
     }
@@ -458,16 +794,18 @@ impl<H, M> ConstantMedium<H, M> {
 impl<H, M> Hittable for ConstantMedium<H, M>
 where
     H: Hittable,
-    M: Material,
+    M: Material + Send,
 {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
-        if let Some(mut hit) = self.boundary.hit(ray, Interval::new(f64::MIN, f64::MAX)) {
-            if let Some(mut hit2) = self
-                .boundary
-                .hit(ray, Interval::new(hit.t + 0.0001, f64::MAX))
+    fn hit(&self, ray: &Ray, ray_t: Interval, rng: &mut SmallRng) -> Option<HitRecord<'_>> {
+        stats::count(Counter::MediumTests);
+        if let Some(mut hit) = self
+            .boundary
+            .hit(ray, Interval::new(f64::MIN, f64::MAX), rng)
+        {
+            if let Some(mut hit2) =
+                self.boundary
+                    .hit(ray, Interval::new(hit.t + 0.0001, f64::MAX), rng)
             {
-                let mut rand = rand::thread_rng();
-
                 if hit.t < ray_t.min {
                     hit.t = ray_t.min;
                 }
@@ -486,7 +824,7 @@ where
 
                 let ray_length = ray.direction.length();
                 let distance_inside_boundary = (hit2.t - hit.t) * ray_length;
-                let hit_distance = self.neg_inv_density * rand.gen::<f64>().ln();
+                let hit_distance = self.neg_inv_density * rng.gen::<f64>().ln();
 
                 if hit_distance > distance_inside_boundary {
                     return None;
@@ -13,8 +13,8 @@ impl Interval {
 
     pub fn empty() -> Interval {
         Interval {
-            min: 1.0,
-            max: -1.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
         }
     }
 
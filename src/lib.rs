@@ -0,0 +1,37 @@
+//! A path tracer following the "Ray Tracing in One Weekend" series.
+//!
+//! Build a world from [`Hittable`] primitives, collect them into a [`Node`] and hand it to
+//! [`Camera::render`], or describe everything in a [`Scene`] file and let it do the wiring.
+
+pub mod aabb;
+pub mod builtin;
+pub mod bvh;
+pub mod camera;
+pub mod checkpoint;
+pub mod distributed;
+pub mod film;
+pub mod filter;
+pub mod hittable;
+pub mod interval;
+pub mod material;
+pub mod matrix;
+pub mod mesh;
+pub mod onb;
+pub mod progress;
+pub mod ray;
+pub mod sampler;
+pub mod scene;
+pub mod stats;
+pub mod texture;
+pub mod tile;
+pub mod tonemap;
+pub mod vec3;
+
+pub use bvh::Node;
+pub use camera::Camera;
+pub use film::Film;
+pub use hittable::{HitRecord, Hittable};
+pub use material::Material;
+pub use scene::Scene;
+pub use texture::Texture;
+pub use vec3::Vec3;
@@ -1,751 +1,429 @@
-use crate::bvh::Node;
-use crate::camera::Camera;
-use crate::hittable::{make_box, ConstantMedium, Hittable, Quad, RotateY, Sphere, Translate};
-use crate::material::{Dielectric, DiffuseLight, Isotropic, Lambertian, Metal};
-use crate::texture::{Checker, Image, Noise, SolidColor};
-use crate::vec3::Vec3;
-use clap::Parser;
-use image::RgbImage;
-use rand::Rng;
-use std::f64::consts::PI;
-
-mod aabb;
-mod bvh;
-mod camera;
-mod hittable;
-mod interval;
-mod material;
-mod ray;
-mod texture;
-mod vec3;
+use clap::{Parser, ValueEnum};
+use ray_tracing::builtin;
+use ray_tracing::bvh::Split;
+use ray_tracing::camera::Crop;
+use ray_tracing::checkpoint::Checkpoint;
+use ray_tracing::distributed::{self, Address};
+use ray_tracing::film::{self, Film};
+use ray_tracing::filter::FilterKind;
+use ray_tracing::progress::Bar;
+use ray_tracing::sampler::SamplerKind;
+use ray_tracing::scene::Scene;
+use ray_tracing::stats::Stats;
+use ray_tracing::tile::TileOrder;
+use ray_tracing::tonemap::{Aces, AgX, Clamp, DisplayTransform, ExtendedReinhard, Reinhard};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Parser)]
 struct Args {
-    #[arg(long)]
-    scene: String,
+    /// Name of a built-in scene or path to a scene file
+    #[arg(long, required_unless_present = "worker")]
+    scene: Option<String>,
+    /// Output image; `.exr` and `.hdr` files keep the full linear radiance
     #[arg(long, default_value = "temp.png")]
-    file: String,
+    file: PathBuf,
+    /// Render in passes of this many samples per pixel, saving the image after each pass
+    #[arg(long)]
+    pass_samples: Option<u32>,
+    /// With --pass-samples, save the image at most once every this many seconds
+    #[arg(long, requires = "pass_samples")]
+    checkpoint_secs: Option<u64>,
+    /// Tone mapping operator used for 8-bit output
+    #[arg(long, value_enum, default_value_t = ToneMap::Clamp)]
+    tone_map: ToneMap,
+    /// Exposure adjustment in stops applied before tone mapping
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    exposure: f64,
+    /// Luminance that maps to white with the extended Reinhard operator
+    #[arg(long, default_value_t = 4.0, value_parser = parse_white)]
+    white: f64,
+    /// Save the accumulated radiance here whenever the image is saved, so the render can be resumed
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Continue the render saved in --checkpoint instead of starting over
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+    /// Add at most this many samples per pixel in this run
+    #[arg(long)]
+    sample_budget: Option<u32>,
+    /// Seed for every random decision made while rendering, overriding the scene's
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Where sample positions and directions come from, overriding the scene's
+    #[arg(long, value_enum)]
+    sampler: Option<Sampling>,
+    /// Reconstruction filter weighing samples into nearby pixels, overriding the scene's
+    #[arg(long, value_enum)]
+    filter: Option<PixelFilter>,
+    /// Width and height of the tiles the image is rendered in, overriding the scene's
+    #[arg(long)]
+    tile_size: Option<u32>,
+    /// Order the tiles are rendered in, overriding the scene's
+    #[arg(long, value_enum)]
+    tile_order: Option<Tiling>,
+    /// How BVHs choose where to split their primitives, overriding the scene's
+    #[arg(long, value_enum)]
+    bvh: Option<BvhSplit>,
+    /// Most primitives a BVH leaf may hold, overriding the scene's
+    #[arg(long)]
+    bvh_leaf_size: Option<usize>,
+    /// Render only the window x0,y0,x1,y1, given in pixels or, with decimal points, as fractions
+    /// of the image size
+    #[arg(long, value_parser = parse_crop)]
+    crop: Option<Crop>,
+    /// With --crop, paste the window into a copy of this full-size image instead of saving it alone
+    #[arg(long, requires = "crop")]
+    paste: Option<PathBuf>,
+    /// Stop sampling pixels whose relative error falls below this, overriding the scene's
+    #[arg(long)]
+    adaptive_threshold: Option<f64>,
+    /// With adaptive sampling, samples every pixel takes before it may stop, overriding the scene's
+    #[arg(long)]
+    min_samples: Option<u32>,
+    /// Save an image of how many samples each pixel took here once the render finishes
+    #[arg(long)]
+    heatmap: Option<PathBuf>,
+    /// Hand the render out to workers connecting to this `host:port` or `unix:<path>` instead of
+    /// rendering here
+    #[arg(long, conflicts_with = "pass_samples")]
+    coordinator: Option<Address>,
+    /// Render tiles for the coordinator at this address, taking the scene and settings from it
+    #[arg(long, exclusive = true)]
+    worker: Option<Address>,
+    /// Do not draw a progress bar or print statistics at the end
+    #[arg(long)]
+    quiet: bool,
+    /// Save the render statistics here as JSON once the render finishes
+    #[arg(long)]
+    stats_json: Option<PathBuf>,
+    /// Print progress to standard output as one JSON object per line, at most once a second
+    #[arg(long)]
+    progress_json: bool,
+    /// Write the scene description to this path instead of rendering
+    #[arg(long)]
+    export: Option<PathBuf>,
 }
 
-fn main() {
-    let args = Args::parse();
-    let image = match args.scene.as_str() {
-        "triplet" => triplet(),
-        "bouncing" => bouncing_final(),
-        "redblue" => redblue(),
-        "checkered" => checkered(),
-        "earth" => earth(),
-        "perlin" => perlin(),
-        "quads" => quads(),
-        "simple-light" => simple_light(),
-        "cornell" => cornell_box(),
-        "cornell-smoke" => cornell_smoke(),
-        "fancy-full" => fancy(800, 10000, 40),
-        "fancy-light" => fancy(400, 250, 4),
-        _ => panic!("unknown scene"),
-    };
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum ToneMap {
+    Clamp,
+    Reinhard,
+    ExtendedReinhard,
+    Aces,
+    Agx,
+}
 
-    image.save(args.file).unwrap();
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum PixelFilter {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    Lanczos,
 }
 
-fn triplet() -> RgbImage {
-    let material_ground = Lambertian {
-        texture: SolidColor::new(Vec3([0.8, 0.8, 0.0])),
-    };
-    let material_center = Lambertian {
-        texture: SolidColor::new(Vec3([0.1, 0.2, 0.5])),
-    };
-    let material_left = Dielectric {
-        refraction_index: 1.5,
-    };
-    let material_bubble = Dielectric {
-        refraction_index: 1.0 / 1.5,
-    };
-    let material_right = Metal {
-        albedo: Vec3([0.8, 0.6, 0.2]),
-        fuzz: 1.0,
-    };
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum Sampling {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
 
-    let world: Vec<Box<dyn Hittable>> = vec![
-        Box::new(Sphere::new(
-            Vec3([0.0, -100.5, -1.0]),
-            100.0,
-            material_ground,
-        )),
-        Box::new(Sphere::new(Vec3([0.0, 0.0, -1.2]), 0.5, material_center)),
-        Box::new(Sphere::new(Vec3([-1.0, 0.0, -1.0]), 0.5, material_left)),
-        Box::new(Sphere::new(Vec3([-1.0, 0.0, -1.0]), 0.4, material_bubble)),
-        Box::new(Sphere::new(Vec3([1.0, 0.0, -1.0]), 0.5, material_right)),
-    ];
-
-    let world = Node::from_list(world);
-
-    let camera = Camera::new(
-        16.0 / 9.0,
-        400,
-        100,
-        50,
-        90.0,
-        Vec3([-2.0, 2.0, 1.0]),
-        Vec3([0.0, 0.0, -1.0]),
-        Vec3([0.0, 1.0, 0.0]),
-        10.0,
-        3.4,
-        Vec3([0.7, 0.8, 1.0]),
-    );
-
-    camera.render(&world)
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum Tiling {
+    Scanline,
+    Spiral,
+    Hilbert,
 }
 
-fn redblue() -> RgbImage {
-    let r = (PI / 4.0).cos();
-    let material_left = Lambertian {
-        texture: SolidColor::new(Vec3::z(1.0)),
-    };
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum BvhSplit {
+    Median,
+    Sah,
+}
 
-    let material_right = Lambertian {
-        texture: SolidColor::new(Vec3::x(1.0)),
-    };
+fn parse_crop(text: &str) -> Result<Crop, String> {
+    let parts: Vec<&str> = text.split(',').map(str::trim).collect();
+    if parts.len() != 4 {
+        return Err("expected four comma-separated values x0,y0,x1,y1".to_string());
+    }
 
-    let world: Vec<Box<dyn Hittable>> = vec![
-        Box::new(Sphere::new(Vec3([-r, 0.0, -1.0]), r, material_left)),
-        Box::new(Sphere::new(Vec3([r, 0.0, -1.0]), r, material_right)),
-    ];
-
-    let world = Node::from_list(world);
-
-    let camera = Camera::new(
-        16.0 / 9.0,
-        400,
-        100,
-        50,
-        90.0,
-        Vec3([0.0, 0.0, 1.0]),
-        Vec3([0.0, 0.0, 0.0]),
-        Vec3([0.0, 1.0, 0.0]),
-        0.0,
-        10.0,
-        Vec3([0.7, 0.8, 1.0]),
-    );
-
-    camera.render(&world)
+    if parts.iter().any(|part| part.contains('.')) {
+        let mut window = [0.0; 4];
+        for (value, part) in window.iter_mut().zip(&parts) {
+            *value = part.parse().map_err(|e| format!("`{part}`: {e}"))?;
+        }
+        Ok(Crop::Normalized(window))
+    } else {
+        let mut window = [0; 4];
+        for (value, part) in window.iter_mut().zip(&parts) {
+            *value = part.parse().map_err(|e| format!("`{part}`: {e}"))?;
+        }
+        Ok(Crop::Pixels(window))
+    }
 }
 
-fn bouncing_final() -> RgbImage {
-    let ground_material = Lambertian {
-        texture: Checker::new(
-            0.32,
-            SolidColor::new(Vec3([0.2, 0.3, 0.1])),
-            SolidColor::new(Vec3([0.9, 0.9, 0.9])),
-        ),
-    };
+fn parse_white(text: &str) -> Result<f64, String> {
+    let white: f64 = text.parse().map_err(|e| format!("`{text}`: {e}"))?;
+    if !(white.is_finite() && white > 0.0) {
+        return Err("must be a positive number".to_string());
+    }
 
-    let mut world: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(
-        Vec3([0.0, -1000.0, 0.0]),
-        1000.0,
-        ground_material,
-    ))];
-
-    let mut rand = rand::thread_rng();
-    for a in -11..11 {
-        for b in -11..11 {
-            let mat = rand.gen::<f64>();
-            let center = Vec3([
-                a as f64 + 0.9 * rand.gen::<f64>(),
-                0.2,
-                b as f64 + 0.9 * rand.gen::<f64>(),
-            ]);
-
-            if (center - Vec3([4.0, 0.2, 0.0])).length() > 0.9 {
-                if mat < 0.9 {
-                    let material = Lambertian {
-                        texture: SolidColor::new(Vec3::random() * Vec3::random()),
-                    };
-                    let end = center + Vec3([0.0, rand.gen(), 0.0]);
-                    world.push(Box::new(Sphere::moving(center, end, 0.2, material)));
-                } else if mat < 0.95 {
-                    let material = Metal {
-                        albedo: Vec3::random_within(0.5, 1.0),
-                        fuzz: rand.gen::<f64>(),
-                    };
-                    world.push(Box::new(Sphere::new(center, 0.2, material)));
-                } else {
-                    let material = Dielectric {
-                        refraction_index: 1.5,
-                    };
-                    world.push(Box::new(Sphere::new(center, 0.2, material)));
+    Ok(white)
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    if let Some(address) = &args.worker {
+        return match distributed::work(address) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{e}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let name = args
+        .scene
+        .as_deref()
+        .expect("clap requires --scene without --worker");
+    let (mut scene, base) = match builtin::scene(name) {
+        Some(scene) => (scene, PathBuf::from(".")),
+        None => {
+            let path = Path::new(name);
+            if !path.is_file() {
+                eprintln!("unknown scene `{name}`");
+                return ExitCode::FAILURE;
+            }
+
+            match Scene::load(path) {
+                Ok(scene) => (scene, path.parent().unwrap_or(Path::new(".")).to_path_buf()),
+                Err(e) => {
+                    eprintln!("{e}");
+                    return ExitCode::FAILURE;
                 }
             }
         }
+    };
+
+    if let Some(seed) = args.seed {
+        scene.camera = scene.camera.seed(seed);
     }
 
-    let material_1 = Dielectric {
-        refraction_index: 1.5,
-    };
-    world.push(Box::new(Sphere::new(
-        Vec3([0.0, 1.0, 0.0]),
-        1.0,
-        material_1,
-    )));
-
-    let material_2 = Lambertian {
-        texture: SolidColor::new(Vec3([0.4, 0.2, 0.1])),
-    };
-    world.push(Box::new(Sphere::new(
-        Vec3([-4.0, 1.0, 0.0]),
-        1.0,
-        material_2,
-    )));
-
-    let material_3 = Metal {
-        albedo: Vec3([0.7, 0.6, 0.5]),
-        fuzz: 0.0,
-    };
-    world.push(Box::new(Sphere::new(
-        Vec3([4.0, 1.0, 0.0]),
-        1.0,
-        material_3,
-    )));
-
-    let world = Node::from_list(world);
-
-    let camera = Camera::new(
-        16.0 / 9.0,
-        400,
-        100,
-        50,
-        20.0,
-        Vec3([13.0, 2.0, 3.0]),
-        Vec3([0.0, 0.0, 0.0]),
-        Vec3([0.0, 1.0, 0.0]),
-        0.6,
-        10.0,
-        Vec3([0.7, 0.8, 1.0]),
-    );
-
-    camera.render(&world)
-}
+    if let Some(sampling) = args.sampler {
+        scene.camera = scene.camera.sampler(match sampling {
+            Sampling::Independent => SamplerKind::Independent,
+            Sampling::Stratified => SamplerKind::Stratified,
+            Sampling::Halton => SamplerKind::Halton,
+            Sampling::Sobol => SamplerKind::Sobol,
+            Sampling::BlueNoise => SamplerKind::BlueNoise,
+        });
+    }
 
-fn checkered() -> RgbImage {
-    let ground_material = Lambertian {
-        texture: Checker::new(
-            0.32,
-            SolidColor::new(Vec3([0.2, 0.3, 0.1])),
-            SolidColor::new(Vec3([0.9, 0.9, 0.9])),
-        ),
-    };
+    if let Some(filter) = args.filter {
+        scene.camera = scene.camera.filter(match filter {
+            PixelFilter::Box => FilterKind::Box,
+            PixelFilter::Tent => FilterKind::Tent,
+            PixelFilter::Gaussian => FilterKind::Gaussian,
+            PixelFilter::Mitchell => FilterKind::Mitchell,
+            PixelFilter::Lanczos => FilterKind::Lanczos,
+        });
+    }
 
-    let world: Vec<Box<dyn Hittable>> = vec![
-        Box::new(Sphere::new(
-            Vec3([0.0, -10.0, 0.0]),
-            10.0,
-            ground_material.clone(),
-        )),
-        Box::new(Sphere::new(Vec3([0.0, 10.0, 0.0]), 10.0, ground_material)),
-    ];
-
-    let world = Node::from_list(world);
-
-    let camera = Camera::new(
-        16.0 / 9.0,
-        400,
-        100,
-        50,
-        20.0,
-        Vec3([13.0, 2.0, 3.0]),
-        Vec3([0.0, 0.0, 0.0]),
-        Vec3([0.0, 1.0, 0.0]),
-        0.0,
-        10.0,
-        Vec3([0.7, 0.8, 1.0]),
-    );
-
-    camera.render(&world)
-}
+    if let Some(tile_size) = args.tile_size {
+        scene.camera = scene.camera.tile_size(tile_size);
+    }
 
-fn earth() -> RgbImage {
-    let earth_texture = image::open("earthmap.jpg").unwrap().into_rgb8();
-    let earth_surface = Lambertian {
-        texture: Image::new(earth_texture),
-    };
-    let globe = Sphere::new(Vec3::scalar(0.0), 2.0, earth_surface);
-
-    let camera = Camera::new(
-        16.0 / 9.0,
-        400,
-        100,
-        50,
-        20.0,
-        Vec3([0.0, 0.0, 12.0]),
-        Vec3([0.0, 0.0, 0.0]),
-        Vec3([0.0, 1.0, 0.0]),
-        0.0,
-        10.0,
-        Vec3([0.7, 0.8, 1.0]),
-    );
-
-    camera.render(&globe)
-}
+    if let Some(tiling) = args.tile_order {
+        scene.camera = scene.camera.tile_order(match tiling {
+            Tiling::Scanline => TileOrder::Scanline,
+            Tiling::Spiral => TileOrder::Spiral,
+            Tiling::Hilbert => TileOrder::Hilbert,
+        });
+    }
 
-fn perlin() -> RgbImage {
-    let ground_material = Lambertian {
-        texture: Noise::<256>::new(4.0),
-    };
+    if let Some(split) = args.bvh {
+        scene.bvh.split = match split {
+            BvhSplit::Median => Split::Median,
+            BvhSplit::Sah => Split::Sah,
+        };
+    }
 
-    let world: Vec<Box<dyn Hittable>> = vec![
-        Box::new(Sphere::new(
-            Vec3([0.0, -1000.0, 0.0]),
-            1000.0,
-            ground_material.clone(),
-        )),
-        Box::new(Sphere::new(Vec3([0.0, 2.0, 0.0]), 2.0, ground_material)),
-    ];
-
-    let world = Node::from_list(world);
-
-    let camera = Camera::new(
-        16.0 / 9.0,
-        400,
-        100,
-        50,
-        20.0,
-        Vec3([13.0, 2.0, 3.0]),
-        Vec3([0.0, 0.0, 0.0]),
-        Vec3([0.0, 1.0, 0.0]),
-        0.0,
-        10.0,
-        Vec3([0.7, 0.8, 1.0]),
-    );
-
-    camera.render(&world)
-}
+    if let Some(leaf_size) = args.bvh_leaf_size {
+        scene.bvh.leaf_size = leaf_size;
+    }
 
-fn quads() -> RgbImage {
-    let world: Vec<Box<dyn Hittable>> = vec![
-        Box::new(Quad::new(
-            Vec3([-3.0, -2.0, 5.0]),
-            Vec3([0.0, 0.0, -4.0]),
-            Vec3([0.0, 4.0, 0.0]),
-            Lambertian {
-                texture: SolidColor::new(Vec3([1.0, 0.2, 0.2])),
-            },
-        )),
-        Box::new(Quad::new(
-            Vec3([-2.0, -2.0, 0.0]),
-            Vec3([4.0, 0.0, 0.0]),
-            Vec3([0.0, 4.0, 0.0]),
-            Lambertian {
-                texture: SolidColor::new(Vec3([0.2, 1.0, 0.2])),
-            },
-        )),
-        Box::new(Quad::new(
-            Vec3([3.0, -2.0, 1.0]),
-            Vec3([0.0, 0.0, 4.0]),
-            Vec3([0.0, 4.0, 0.0]),
-            Lambertian {
-                texture: SolidColor::new(Vec3([0.2, 0.2, 1.0])),
-            },
-        )),
-        Box::new(Quad::new(
-            Vec3([-2.0, 3.0, 1.0]),
-            Vec3([4.0, 0.0, 0.0]),
-            Vec3([0.0, 0.0, 4.0]),
-            Lambertian {
-                texture: SolidColor::new(Vec3([1.0, 0.5, 0.0])),
-            },
-        )),
-        Box::new(Quad::new(
-            Vec3([-2.0, -3.0, 5.0]),
-            Vec3([4.0, 0.0, 0.0]),
-            Vec3([0.0, 0.0, -4.0]),
-            Lambertian {
-                texture: SolidColor::new(Vec3([0.2, 0.8, 0.8])),
-            },
-        )),
-    ];
-
-    let world = Node::from_list(world);
-
-    let camera = Camera::new(
-        16.0 / 9.0,
-        400,
-        100,
-        50,
-        80.0,
-        Vec3([0.0, 0.0, 9.0]),
-        Vec3([0.0, 0.0, 0.0]),
-        Vec3([0.0, 1.0, 0.0]),
-        0.0,
-        10.0,
-        Vec3([0.7, 0.8, 1.0]),
-    );
-
-    camera.render(&world)
-}
+    if let Some(crop) = args.crop {
+        scene.camera = scene.camera.crop(Some(crop));
+    }
 
-fn simple_light() -> RgbImage {
-    let ground_material = Lambertian {
-        texture: Noise::<256>::new(4.0),
-    };
+    if let Some(threshold) = args.adaptive_threshold {
+        scene.camera = scene.camera.adaptive_threshold(threshold);
+    }
 
-    let light = DiffuseLight::new(SolidColor::new(Vec3([4.0, 4.0, 4.0])));
-    let world: Vec<Box<dyn Hittable>> = vec![
-        Box::new(Sphere::new(
-            Vec3([0.0, -1000.0, 0.0]),
-            1000.0,
-            ground_material.clone(),
-        )),
-        Box::new(Sphere::new(Vec3([0.0, 2.0, 0.0]), 2.0, ground_material)),
-        Box::new(Sphere::new(Vec3([0.0, 7.0, 0.0]), 2.0, light.clone())),
-        Box::new(Quad::new(
-            Vec3([3.0, 1.0, -2.0]),
-            Vec3([2.0, 0.0, 0.0]),
-            Vec3([0.0, 2.0, 0.0]),
-            light,
-        )),
-    ];
-
-    let world = Node::from_list(world);
-
-    let camera = Camera::new(
-        16.0 / 9.0,
-        400,
-        100,
-        50,
-        20.0,
-        Vec3([26.0, 3.0, 6.0]),
-        Vec3([0.0, 2.0, 0.0]),
-        Vec3([0.0, 1.0, 0.0]),
-        0.0,
-        10.0,
-        Vec3([0.0, 0.0, 0.0]),
-    );
-
-    camera.render(&world)
-}
+    if let Some(min_samples) = args.min_samples {
+        scene.camera = scene.camera.min_samples_per_pixel(min_samples);
+    }
 
-fn cornell_box() -> RgbImage {
-    let green = Lambertian {
-        texture: SolidColor::new(Vec3([0.12, 0.45, 0.15])),
-    };
-    let red = Lambertian {
-        texture: SolidColor::new(Vec3([0.65, 0.05, 0.05])),
-    };
-    let light = DiffuseLight::new(SolidColor::new(Vec3([15.0, 15.0, 15.0])));
-    let white = Lambertian {
-        texture: SolidColor::new(Vec3([0.73, 0.73, 0.73])),
+    if let Some(export) = args.export {
+        return match scene.save(&export) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{e}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let (world, lights, mut camera) = match scene.build(&base) {
+        Ok(built) => built,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
     };
 
-    let world: Vec<Box<dyn Hittable>> = vec![
-        Box::new(Quad::new(
-            Vec3([555.0, 0.0, 0.0]),
-            Vec3([0.0, 555.0, 0.0]),
-            Vec3([0.0, 0.0, 555.0]),
-            green,
-        )),
-        Box::new(Quad::new(
-            Vec3([0.0, 0.0, 0.0]),
-            Vec3([0.0, 555.0, 0.0]),
-            Vec3([0.0, 0.0, 555.0]),
-            red,
-        )),
-        Box::new(Quad::new(
-            Vec3([343.0, 554.0, 332.0]),
-            Vec3([-130.0, 0.0, 0.0]),
-            Vec3([0.0, 0.0, -105.0]),
-            light,
-        )),
-        Box::new(Quad::new(
-            Vec3([0.0, 0.0, 0.0]),
-            Vec3([555.0, 0.0, 0.0]),
-            Vec3([0.0, 0.0, 555.0]),
-            white.clone(),
-        )),
-        Box::new(Quad::new(
-            Vec3([555.0, 555.0, 555.0]),
-            Vec3([-555.0, 0.0, 0.0]),
-            Vec3([0.0, 0.0, -555.0]),
-            white.clone(),
-        )),
-        Box::new(Quad::new(
-            Vec3([0.0, 0.0, 555.0]),
-            Vec3([555.0, 0.0, 0.0]),
-            Vec3([0.0, 555.0, 0.0]),
-            white.clone(),
-        )),
-        Box::new(Translate::new(
-            RotateY::new(
-                make_box(
-                    Vec3([0.0, 0.0, 0.0]),
-                    Vec3([165.0, 330.0, 165.0]),
-                    white.clone(),
-                ),
-                15.0,
-            ),
-            Vec3([265.0, 0.0, 295.0]),
-        )),
-        Box::new(Translate::new(
-            RotateY::new(
-                make_box(Vec3([0.0, 0.0, 0.0]), Vec3([165.0, 165.0, 165.0]), white),
-                -18.0,
-            ),
-            Vec3([130.0, 0.0, 65.0]),
-        )),
-    ];
-
-    let world = Node::from_list(world);
-
-    let camera = Camera::new(
-        1.0,
-        600,
-        200,
-        50,
-        40.0,
-        Vec3([278.0, 278.0, -800.0]),
-        Vec3([278.0, 278.0, 0.0]),
-        Vec3([0.0, 1.0, 0.0]),
-        0.0,
-        10.0,
-        Vec3([0.0, 0.0, 0.0]),
-    );
-
-    camera.render(&world)
-}
+    let mut bar = (!args.quiet && io::stderr().is_terminal()).then(Bar::new);
+    let mut json = args.progress_json.then_some(None::<Instant>);
+    camera.on_progress(move |progress| {
+        if let Some(bar) = &mut bar {
+            bar.update(progress);
+        }
 
-fn cornell_smoke() -> RgbImage {
-    let green = Lambertian {
-        texture: SolidColor::new(Vec3([0.12, 0.45, 0.15])),
-    };
-    let red = Lambertian {
-        texture: SolidColor::new(Vec3([0.65, 0.05, 0.05])),
+        if let Some(printed) = &mut json {
+            if progress.done() || printed.is_none_or(|p| p.elapsed() >= Duration::from_secs(1)) {
+                println!("{}", progress.to_json());
+                *printed = Some(Instant::now());
+            }
+        }
+    });
+
+    if let Some(path) = &args.paste {
+        if film::is_linear(path) != film::is_linear(&args.file) {
+            eprintln!(
+                "{}: cannot be pasted into {}, as only one of them is linear (.exr or .hdr)",
+                path.display(),
+                args.file.display()
+            );
+            return ExitCode::FAILURE;
+        }
+
+        let (width, height) = camera.resolution();
+        match image::image_dimensions(path) {
+            Ok(size) if size == (width, height) => {}
+            Ok((w, h)) => {
+                eprintln!("{}: is {w}x{h}, not {width}x{height}", path.display());
+                return ExitCode::FAILURE;
+            }
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let scene_hash = scene.content_hash();
+    let mut film = match (&args.checkpoint, args.resume) {
+        (Some(path), true) => match Checkpoint::resume(path, &scene.camera, scene_hash) {
+            Ok(film) => film,
+            Err(e) => {
+                eprintln!("{e}");
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => camera.film(),
     };
-    let light = DiffuseLight::new(SolidColor::new(Vec3([7.0, 7.0, 7.0])));
-    let white = Lambertian {
-        texture: SolidColor::new(Vec3([0.73, 0.73, 0.73])),
+
+    let target = match args.sample_budget {
+        Some(budget) => film
+            .max_samples()
+            .saturating_add(budget)
+            .min(camera.samples_per_pixel()),
+        None => camera.samples_per_pixel(),
     };
 
-    let world: Vec<Box<dyn Hittable>> = vec![
-        Box::new(Quad::new(
-            Vec3([555.0, 0.0, 0.0]),
-            Vec3([0.0, 555.0, 0.0]),
-            Vec3([0.0, 0.0, 555.0]),
-            green,
-        )),
-        Box::new(Quad::new(
-            Vec3([0.0, 0.0, 0.0]),
-            Vec3([0.0, 555.0, 0.0]),
-            Vec3([0.0, 0.0, 555.0]),
-            red,
-        )),
-        Box::new(Quad::new(
-            Vec3([113.0, 554.0, 127.0]),
-            Vec3([330.0, 0.0, 0.0]),
-            Vec3([0.0, 0.0, 305.0]),
-            light,
-        )),
-        Box::new(Quad::new(
-            Vec3([0.0, 555.0, 0.0]),
-            Vec3([555.0, 0.0, 0.0]),
-            Vec3([0.0, 0.0, 555.0]),
-            white.clone(),
-        )),
-        Box::new(Quad::new(
-            Vec3([0.0, 0.0, 0.0]),
-            Vec3([555.0, 0.0, 0.0]),
-            Vec3([0.0, 0.0, 555.0]),
-            white.clone(),
-        )),
-        Box::new(Quad::new(
-            Vec3([0.0, 0.0, 555.0]),
-            Vec3([555.0, 0.0, 0.0]),
-            Vec3([0.0, 555.0, 0.0]),
-            white.clone(),
-        )),
-        Box::new(ConstantMedium::new(
-            Translate::new(
-                RotateY::new(
-                    make_box(
-                        Vec3([0.0, 0.0, 0.0]),
-                        Vec3([165.0, 330.0, 165.0]),
-                        white.clone(),
-                    ),
-                    15.0,
-                ),
-                Vec3([265.0, 0.0, 295.0]),
-            ),
-            0.01,
-            Isotropic::new(SolidColor::new(Vec3::scalar(0.0))),
-        )),
-        Box::new(ConstantMedium::new(
-            Translate::new(
-                RotateY::new(
-                    make_box(Vec3([0.0, 0.0, 0.0]), Vec3([165.0, 165.0, 165.0]), white),
-                    -18.0,
-                ),
-                Vec3([130.0, 0.0, 65.0]),
-            ),
-            0.01,
-            Isotropic::new(SolidColor::new(Vec3::scalar(1.0))),
-        )),
-    ];
-
-    let world = Node::from_list(world);
-
-    let camera = Camera::new(
-        1.0,
-        600,
-        200,
-        50,
-        40.0,
-        Vec3([278.0, 278.0, -800.0]),
-        Vec3([278.0, 278.0, 0.0]),
-        Vec3([0.0, 1.0, 0.0]),
-        0.0,
-        10.0,
-        Vec3([0.0, 0.0, 0.0]),
-    );
-
-    camera.render(&world)
-}
+    let display = match args.tone_map {
+        ToneMap::Clamp => DisplayTransform::new(Clamp),
+        ToneMap::Reinhard => DisplayTransform::new(Reinhard),
+        ToneMap::ExtendedReinhard => DisplayTransform::new(ExtendedReinhard { white: args.white }),
+        ToneMap::Aces => DisplayTransform::new(Aces),
+        ToneMap::Agx => DisplayTransform::new(AgX),
+    }
+    .exposure(args.exposure);
+
+    // The checkpoint is written first, so the samples survive an image that cannot be saved.
+    let save = |film: &Film| {
+        if let Some(path) = &args.checkpoint {
+            let checkpoint = Checkpoint {
+                camera: scene.camera.clone(),
+                scene_hash,
+                film: film.clone(),
+            };
+            if let Err(e) = checkpoint.save(path) {
+                eprintln!("{e}");
+            }
+        }
+        match &args.paste {
+            Some(background) => film.save_pasted(camera.crop(), background, &args.file, &display),
+            None => film.cropped(camera.crop()).save(&args.file, &display),
+        }
+    };
 
-fn fancy(image_width: u32, samples: u32, max_depth: u32) -> RgbImage {
-    let mut rand = rand::thread_rng();
+    let interval = args.checkpoint_secs.map(Duration::from_secs);
+    let mut last_save = Instant::now();
+    let pass_samples = args.pass_samples.unwrap_or(target);
+    let mut save_error = None;
+    match &args.coordinator {
+        Some(address) => {
+            let coordinated =
+                distributed::coordinate(address, &scene, &base, &camera, &mut film, target);
+            if let Err(e) = coordinated {
+                eprintln!("{e}");
+                return ExitCode::FAILURE;
+            }
+        }
+        None => {
+            camera.render_progressive(&world, &lights, &mut film, target, pass_samples, |film| {
+                if args.pass_samples.is_some() && interval.is_none_or(|i| last_save.elapsed() >= i)
+                {
+                    if let Err(e) = save(film) {
+                        save_error.get_or_insert(e);
+                    }
+                    last_save = Instant::now();
+                }
+            });
+        }
+    }
+    if let Some(e) = save_error.or(save(&film).err()) {
+        eprintln!("{}: {e}", args.file.display());
+        return ExitCode::FAILURE;
+    }
 
-    let ground = Lambertian {
-        texture: SolidColor::new(Vec3([0.48, 0.83, 0.53])),
-    };
+    let stats = Stats::collect();
+    if !args.quiet {
+        eprint!("{stats}");
+    }
 
-    let mut boxes: Vec<Box<dyn Hittable>> = Vec::new();
-    for i in 0..20 {
-        for j in 0..20 {
-            let w = 100.0;
-            let x0 = -1000.0 + i as f64 * w;
-            let z0 = -1000.0 + j as f64 * w;
-            let y0 = 0.0;
-            let x1 = x0 + w;
-            let y1 = rand.gen::<f64>() * 100.0 + 1.0;
-            let z1 = z0 + w;
-
-            boxes.push(Box::new(make_box(
-                Vec3([x0, y0, z0]),
-                Vec3([x1, y1, z1]),
-                ground.clone(),
-            )));
+    if let Some(path) = &args.stats_json {
+        if let Err(e) = fs::write(path, stats.to_json() + "\n") {
+            eprintln!("{}: {e}", path.display());
+            return ExitCode::FAILURE;
         }
     }
 
-    let earth_texture = image::open("earthmap.jpg").unwrap().into_rgb8();
-
-    let mut world: Vec<Box<dyn Hittable>> = vec![
-        Box::new(Node::from_list(boxes)),
-        Box::new(Quad::new(
-            Vec3([123.0, 554.0, 147.0]),
-            Vec3([300.0, 0.0, 0.0]),
-            Vec3([0.0, 0.0, 265.0]),
-            DiffuseLight::new(SolidColor::new(Vec3([7.0, 7.0, 7.0]))),
-        )),
-        Box::new(Sphere::new(
-            Vec3([260.0, 150.0, 45.0]),
-            50.0,
-            Dielectric {
-                refraction_index: 1.5,
-            },
-        )),
-        Box::new(Sphere::new(
-            Vec3([0.0, 150.0, 145.0]),
-            50.0,
-            Metal {
-                albedo: Vec3([0.8, 0.8, 0.9]),
-                fuzz: 1.0,
-            },
-        )),
-        Box::new(Sphere::new(
-            Vec3([360.0, 150.0, 145.0]),
-            70.0,
-            Dielectric {
-                refraction_index: 1.5,
-            },
-        )),
-        Box::new(ConstantMedium::new(
-            Sphere::new(
-                Vec3([360.0, 150.0, 145.0]),
-                70.0,
-                Dielectric {
-                    refraction_index: 1.5,
-                },
-            ),
-            0.2,
-            Isotropic::new(SolidColor::new(Vec3([0.2, 0.4, 0.9]))),
-        )),
-        Box::new(ConstantMedium::new(
-            Sphere::new(
-                Vec3([0.0, 0.0, 0.0]),
-                5000.0,
-                Dielectric {
-                    refraction_index: 1.5,
-                },
-            ),
-            0.0001,
-            Isotropic::new(SolidColor::new(Vec3([1.0, 1.0, 1.0]))),
-        )),
-        Box::new(Sphere::new(
-            Vec3([400.0, 200.0, 400.0]),
-            100.0,
-            Lambertian {
-                texture: Image::new(earth_texture),
-            },
-        )),
-        Box::new(Sphere::new(
-            Vec3([220.0, 280.0, 300.0]),
-            80.0,
-            Lambertian {
-                texture: Noise::<256>::new(0.2),
-            },
-        )),
-    ];
-
-    let center1 = Vec3::scalar(400.0);
-    let center2 = center1 + Vec3([30.0, 0.0, 0.0]);
-    world.push(Box::new(Sphere::moving(
-        center1,
-        center2,
-        50.0,
-        Lambertian {
-            texture: SolidColor::new(Vec3([0.7, 0.3, 0.1])),
-        },
-    )));
-
-    let mut boxes2: Vec<Box<dyn Hittable>> = Vec::new();
-    for _ in 0..1000 {
-        boxes2.push(Box::new(Sphere::new(
-            Vec3::random_within(0.0, 165.0),
-            10.0,
-            Lambertian {
-                texture: SolidColor::new(Vec3::scalar(0.73)),
-            },
-        )));
+    if let Some(path) = &args.heatmap {
+        if let Err(e) = film.cropped(camera.crop()).sample_heatmap().save(path) {
+            eprintln!("{}: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
     }
 
-    world.push(Box::new(Translate::new(
-        RotateY::new(Node::from_list(boxes2), 15.0),
-        Vec3([-100.0, 270.0, 395.0]),
-    )));
-
-    let world = Node::from_list(world);
-
-    let camera = Camera::new(
-        1.0,
-        image_width,
-        samples,
-        max_depth,
-        40.0,
-        Vec3([478.0, 278.0, -600.0]),
-        Vec3([278.0, 278.0, 0.0]),
-        Vec3([0.0, 1.0, 0.0]),
-        0.0,
-        10.0,
-        Vec3([0.0, 0.0, 0.0]),
-    );
-
-    camera.render(&world)
+    ExitCode::SUCCESS
 }
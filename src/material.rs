@@ -1,19 +1,77 @@
 use crate::hittable::HitRecord;
-use crate::ray::Ray;
+use crate::onb::Onb;
+use crate::sampler::{cosine_hemisphere, uniform_sphere};
 use crate::texture::Texture;
 use crate::vec3::Vec3;
-use rand::Rng;
+use std::f64::consts::PI;
+use std::sync::Arc;
 
+/// A direction picked by [`Material::sample`].
+#[derive(Debug, Copy, Clone)]
+pub struct BsdfSample {
+    /// Unit direction the path continues in, pointing away from the surface.
+    pub direction: Vec3,
+    /// The BSDF times the cosine term for `direction`, i.e. what [`Material::eval`] returns.
+    pub value: Vec3,
+    /// Density over solid angle with which `direction` was picked.
+    pub pdf: f64,
+    /// Whether `direction` came from a specular lobe. Such lobes are a single direction that
+    /// `eval` and `pdf` never report, and `value / pdf` is their weight with `pdf` set to 1.
+    pub delta: bool,
+}
+
+impl BsdfSample {
+    /// The throughput of the path continuing along `direction`.
+    pub fn weight(&self) -> Vec3 {
+        self.value / self.pdf
+    }
+}
+
+/// Surface and volume scattering. Directions are unit vectors: `wo` points back along the
+/// incoming ray and `wi` away from the hit point, towards where light arrives from.
 pub trait Material: Sync {
-    fn scatter(&self, _ray: &Ray, _hit: &HitRecord) -> Option<(Ray, Vec3)> {
+    /// Picks a direction to continue a path in from the uniform values `u`, or `None` if the
+    /// path is absorbed.
+    fn sample(&self, _hit: &HitRecord, _wo: Vec3, _u: [f64; 2]) -> Option<BsdfSample> {
         None
     }
 
+    /// The BSDF times the cosine of `wi` with the normal, leaving out any delta lobes.
+    fn eval(&self, _hit: &HitRecord, _wo: Vec3, _wi: Vec3) -> Vec3 {
+        Vec3::scalar(0.0)
+    }
+
+    /// Density over solid angle with which `sample` picks `wi`, leaving out any delta lobes.
+    fn pdf(&self, _hit: &HitRecord, _wo: Vec3, _wi: Vec3) -> f64 {
+        0.0
+    }
+
     fn emitted(&self, _u: f64, _v: f64, _point: Vec3) -> Vec3 {
         Vec3::scalar(0.0)
     }
 }
 
+impl<M> Material for Arc<M>
+where
+    M: Material + Send + ?Sized,
+{
+    fn sample(&self, hit: &HitRecord, wo: Vec3, u: [f64; 2]) -> Option<BsdfSample> {
+        self.as_ref().sample(hit, wo, u)
+    }
+
+    fn eval(&self, hit: &HitRecord, wo: Vec3, wi: Vec3) -> Vec3 {
+        self.as_ref().eval(hit, wo, wi)
+    }
+
+    fn pdf(&self, hit: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        self.as_ref().pdf(hit, wo, wi)
+    }
+
+    fn emitted(&self, u: f64, v: f64, point: Vec3) -> Vec3 {
+        self.as_ref().emitted(u, v, point)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Lambertian<T> {
     pub texture: T,
@@ -23,21 +81,28 @@ impl<T> Material for Lambertian<T>
 where
     T: Texture,
 {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<(Ray, Vec3)> {
-        let potential_direction = hit.normal + Vec3::random_unit_vector();
-        let direction = if potential_direction.near_zero() {
-            hit.normal
-        } else {
-            potential_direction
-        };
+    fn sample(&self, hit: &HitRecord, wo: Vec3, u: [f64; 2]) -> Option<BsdfSample> {
+        let direction = Onb::new(hit.normal).transform(cosine_hemisphere(u)).unit();
 
-        let scattered = Ray {
-            origin: hit.point,
+        let pdf = self.pdf(hit, wo, direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
             direction,
-            time: ray.time,
-        };
+            value: self.eval(hit, wo, direction),
+            pdf,
+            delta: false,
+        })
+    }
 
-        Some((scattered, self.texture.value(hit.u, hit.v, hit.point)))
+    fn eval(&self, hit: &HitRecord, wo: Vec3, wi: Vec3) -> Vec3 {
+        self.texture.value(hit.u, hit.v, hit.point) * self.pdf(hit, wo, wi)
+    }
+
+    fn pdf(&self, hit: &HitRecord, _wo: Vec3, wi: Vec3) -> f64 {
+        hit.normal.dot(wi).max(0.0) / PI
     }
 }
 
@@ -46,21 +111,70 @@ pub struct Metal {
     pub fuzz: f64,
 }
 
+impl Metal {
+    /// Density of `normalize(reflected + fuzz * u)` for `u` uniform on the unit sphere. The
+    /// offset points are uniform on a sphere of radius `fuzz` around `reflected`, so the density
+    /// of `wi` is that area density converted to solid angle at each point `wi` passes through.
+    fn fuzz_pdf(&self, reflected: Vec3, wi: Vec3) -> f64 {
+        let b = wi.dot(reflected);
+        let discriminant = b * b - reflected.length_squared() + self.fuzz * self.fuzz;
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+
+        let sqrtd = discriminant.sqrt();
+        [b - sqrtd, b + sqrtd]
+            .into_iter()
+            .filter(|&t| t > 0.0)
+            .map(|t| {
+                let normal = (wi * t - reflected) / self.fuzz;
+                t * t / (4.0 * PI * self.fuzz * self.fuzz * wi.dot(normal).abs().max(1e-8))
+            })
+            .sum()
+    }
+}
+
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<(Ray, Vec3)> {
-        let reflected = Vec3::reflect(ray.direction, hit.normal).unit()
-            + (self.fuzz * Vec3::random_unit_vector());
-        let scattered = Ray {
-            origin: hit.point,
-            direction: reflected,
-            time: ray.time,
-        };
+    fn sample(&self, hit: &HitRecord, wo: Vec3, u: [f64; 2]) -> Option<BsdfSample> {
+        let reflected = Vec3::reflect(-wo, hit.normal).unit();
+        let direction = reflected + (self.fuzz * uniform_sphere(u));
+        if direction.dot(hit.normal) <= 0.0 {
+            return None;
+        }
 
-        if scattered.direction.dot(hit.normal) > 0.0 {
-            Some((scattered, self.albedo))
-        } else {
-            None
+        if self.fuzz <= 0.0 {
+            return Some(BsdfSample {
+                direction: direction.unit(),
+                value: self.albedo,
+                pdf: 1.0,
+                delta: true,
+            });
+        }
+
+        let direction = direction.unit();
+        let pdf = self.fuzz_pdf(reflected, direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            direction,
+            value: self.albedo * pdf,
+            pdf,
+            delta: false,
+        })
+    }
+
+    fn eval(&self, hit: &HitRecord, wo: Vec3, wi: Vec3) -> Vec3 {
+        self.albedo * self.pdf(hit, wo, wi)
+    }
+
+    fn pdf(&self, hit: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        if self.fuzz <= 0.0 || wi.dot(hit.normal) <= 0.0 {
+            return 0.0;
         }
+
+        self.fuzz_pdf(Vec3::reflect(-wo, hit.normal).unit(), wi)
     }
 }
 
@@ -77,34 +191,30 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<(Ray, Vec3)> {
-        let attenuation = Vec3::scalar(1.0);
+    fn sample(&self, hit: &HitRecord, wo: Vec3, u: [f64; 2]) -> Option<BsdfSample> {
         let ri = if hit.front_face {
             1.0 / self.refraction_index
         } else {
             self.refraction_index
         };
 
-        let unit_direction = ray.direction.unit();
-        let cos_theta = (-unit_direction).dot(hit.normal).min(1.0);
+        let unit_direction = -wo;
+        let cos_theta = wo.dot(hit.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta.powi(2)).sqrt();
 
         let cannot_refract = ri * sin_theta > 1.0;
-        let mut rand = rand::thread_rng();
-        let direction =
-            if cannot_refract || Dielectric::reflectance(cos_theta, ri) > rand.gen::<f64>() {
-                Vec3::reflect(unit_direction, hit.normal)
-            } else {
-                Vec3::refract(unit_direction, hit.normal, ri)
-            };
-
-        let scattered = Ray {
-            origin: hit.point,
-            direction,
-            time: ray.time,
+        let direction = if cannot_refract || Dielectric::reflectance(cos_theta, ri) > u[0] {
+            Vec3::reflect(unit_direction, hit.normal)
+        } else {
+            Vec3::refract(unit_direction, hit.normal, ri)
         };
 
-        Some((scattered, attenuation))
+        Some(BsdfSample {
+            direction: direction.unit(),
+            value: Vec3::scalar(1.0),
+            pdf: 1.0,
+            delta: true,
+        })
     }
 }
 
@@ -145,14 +255,23 @@ impl<T> Material for Isotropic<T>
 where
     T: Texture,
 {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<(Ray, Vec3)> {
-        Some((
-            Ray {
-                origin: hit.point,
-                direction: Vec3::random_unit_vector(),
-                time: ray.time,
-            },
-            self.texture.value(hit.u, hit.v, hit.point),
-        ))
+    fn sample(&self, hit: &HitRecord, wo: Vec3, u: [f64; 2]) -> Option<BsdfSample> {
+        let direction = uniform_sphere(u);
+
+        Some(BsdfSample {
+            direction,
+            value: self.eval(hit, wo, direction),
+            pdf: self.pdf(hit, wo, direction),
+            delta: false,
+        })
+    }
+
+    /// The phase function, which has no cosine term as media scatter the same in every direction.
+    fn eval(&self, hit: &HitRecord, wo: Vec3, wi: Vec3) -> Vec3 {
+        self.texture.value(hit.u, hit.v, hit.point) * self.pdf(hit, wo, wi)
+    }
+
+    fn pdf(&self, _hit: &HitRecord, _wo: Vec3, _wi: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
     }
 }
@@ -0,0 +1,285 @@
+use crate::aabb::Aabb;
+use crate::interval::Interval;
+use crate::vec3::Vec3;
+use serde::{Deserialize, Serialize};
+use std::ops::Mul;
+
+/// A 4×4 matrix acting on column vectors, stored row by row. Points are transformed as if their
+/// fourth coordinate were one, and directions as if it were zero.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Matrix4(pub [[f64; 4]; 4]);
+
+impl Matrix4 {
+    pub const IDENTITY: Matrix4 = Matrix4([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    pub fn translation(offset: Vec3) -> Matrix4 {
+        Matrix4([
+            [1.0, 0.0, 0.0, offset[0]],
+            [0.0, 1.0, 0.0, offset[1]],
+            [0.0, 0.0, 1.0, offset[2]],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Rotates by `angle` degrees about `axis`, counterclockwise when looking back along it.
+    pub fn rotation(axis: Vec3, angle: f64) -> Matrix4 {
+        let [x, y, z] = axis.unit().0;
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let t = 1.0 - cos;
+
+        Matrix4([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scaling(factors: Vec3) -> Matrix4 {
+        Matrix4([
+            [factors[0], 0.0, 0.0, 0.0],
+            [0.0, factors[1], 0.0, 0.0],
+            [0.0, 0.0, factors[2], 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Places an object at `from` with its z axis pointing towards `at` and its y axis as close
+    /// to `up` as it can be.
+    pub fn look_at(from: Vec3, at: Vec3, up: Vec3) -> Matrix4 {
+        let w = (at - from).unit();
+        let u = up.cross(w).unit();
+        let v = w.cross(u);
+
+        Matrix4([
+            [u[0], v[0], w[0], from[0]],
+            [u[1], v[1], w[1], from[1]],
+            [u[2], v[2], w[2], from[2]],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Whether the bottom row is `[0, 0, 0, 1]`, so the matrix maps points without projecting.
+    pub fn is_affine(&self) -> bool {
+        self.0[3] == [0.0, 0.0, 0.0, 1.0]
+    }
+
+    pub fn transpose(&self) -> Matrix4 {
+        Matrix4(std::array::from_fn(|i| {
+            std::array::from_fn(|j| self.0[j][i])
+        }))
+    }
+
+    /// The inverse found by Gauss-Jordan elimination, or `None` if the matrix is singular or has
+    /// entries that are not finite.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        if !self.0.iter().flatten().all(|value| value.is_finite()) {
+            return None;
+        }
+        let mut a = self.0;
+        let mut inverse = Matrix4::IDENTITY.0;
+
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let scale = 1.0 / a[column][column];
+            for j in 0..4 {
+                a[column][j] *= scale;
+                inverse[column][j] *= scale;
+            }
+
+            for row in 0..4 {
+                let factor = a[row][column];
+                if row == column || factor == 0.0 {
+                    continue;
+                }
+                for j in 0..4 {
+                    a[row][j] -= factor * a[column][j];
+                    inverse[row][j] -= factor * inverse[column][j];
+                }
+            }
+        }
+
+        Some(Matrix4(inverse))
+    }
+
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let m = &self.0;
+        Vec3(std::array::from_fn(|i| {
+            m[i][0] * p[0] + m[i][1] * p[1] + m[i][2] * p[2] + m[i][3]
+        }))
+    }
+
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.0;
+        Vec3(std::array::from_fn(|i| {
+            m[i][0] * v[0] + m[i][1] * v[1] + m[i][2] * v[2]
+        }))
+    }
+
+    /// The box around the eight transformed corners of `bounds`.
+    pub fn transform_bounds(&self, bounds: &Aabb) -> Aabb {
+        let mut min = Vec3::scalar(f64::INFINITY);
+        let mut max = Vec3::scalar(f64::NEG_INFINITY);
+        for corner in 0..8 {
+            let p = self.transform_point(Vec3([
+                if corner & 1 == 0 {
+                    bounds.x.min
+                } else {
+                    bounds.x.max
+                },
+                if corner & 2 == 0 {
+                    bounds.y.min
+                } else {
+                    bounds.y.max
+                },
+                if corner & 4 == 0 {
+                    bounds.z.min
+                } else {
+                    bounds.z.max
+                },
+            ]));
+            for axis in 0..3 {
+                min.0[axis] = min[axis].min(p[axis]);
+                max.0[axis] = max[axis].max(p[axis]);
+            }
+        }
+
+        Aabb::new(
+            Interval::new(min[0], max[0]),
+            Interval::new(min[1], max[1]),
+            Interval::new(min[2], max[2]),
+        )
+    }
+}
+
+impl Default for Matrix4 {
+    fn default() -> Self {
+        Matrix4::IDENTITY
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Matrix4(std::array::from_fn(|i| {
+            std::array::from_fn(|j| (0..4).map(|k| self.0[i][k] * rhs.0[k][j]).sum())
+        }))
+    }
+}
+
+/// A rotation as a unit quaternion, which unlike a matrix can be interpolated smoothly.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub v: Vec3,
+}
+
+impl Quaternion {
+    /// Rotates by `angle` degrees about `axis`, the same way as [`Matrix4::rotation`]. A zero
+    /// axis gives a quaternion of NaNs.
+    pub fn from_axis_angle(axis: Vec3, angle: f64) -> Quaternion {
+        let (sin, cos) = (angle.to_radians() / 2.0).sin_cos();
+        Quaternion {
+            w: cos,
+            v: axis.unit() * sin,
+        }
+    }
+
+    pub fn dot(&self, rhs: Quaternion) -> f64 {
+        self.w * rhs.w + self.v.dot(rhs.v)
+    }
+
+    /// The angle in radians of the rotation that takes this one to `other` the shorter way round.
+    pub fn angle_to(&self, other: Quaternion) -> f64 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    /// Interpolates at constant angular speed from this rotation at `t = 0` to `other` at
+    /// `t = 1`, taking the shorter way round.
+    pub fn slerp(&self, other: Quaternion, t: f64) -> Quaternion {
+        let mut dot = self.dot(other);
+        let other = if dot < 0.0 {
+            dot = -dot;
+            Quaternion {
+                w: -other.w,
+                v: -other.v,
+            }
+        } else {
+            other
+        };
+
+        // Nearly equal rotations would divide by almost zero, and are close enough to a line.
+        let (a, b) = if dot > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = dot.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+
+        let w = a * self.w + b * other.w;
+        let v = a * self.v + b * other.v;
+        let length = (w * w + v.length_squared()).sqrt();
+        Quaternion {
+            w: w / length,
+            v: v / length,
+        }
+    }
+
+    pub fn to_matrix(&self) -> Matrix4 {
+        let w = self.w;
+        let [x, y, z] = self.v.0;
+
+        Matrix4([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
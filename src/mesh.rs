@@ -0,0 +1,339 @@
+use crate::aabb::Aabb;
+use crate::bvh::{self, Node};
+use crate::hittable::{HitRecord, Hittable, Triangle};
+use crate::interval::Interval;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::ray::Ray;
+use crate::texture::{Image, SolidColor, Texture};
+use crate::vec3::Vec3;
+use rand::rngs::SmallRng;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+use std::{fs, io};
+
+type SharedMaterial = Arc<dyn Material + Send>;
+
+/// A triangle mesh loaded from a Wavefront OBJ file, with its faces collected into a BVH.
+pub struct Mesh {
+    triangles: Node,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    Image(PathBuf, image::ImageError),
+    Empty(PathBuf),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            Error::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+            Error::Image(path, e) => write!(f, "{}: {e}", path.display()),
+            Error::Empty(path) => write!(f, "{}: mesh has no faces", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Mesh {
+    /// Loads an OBJ file, mapping the materials of any referenced `.mtl` libraries onto the
+    /// renderer's own materials.
+    pub fn load(path: &Path, bvh: &bvh::Settings) -> Result<Mesh, Error> {
+        Mesh::parse(path, None, bvh)
+    }
+
+    /// Loads an OBJ file, giving every face `material` and ignoring material libraries.
+    pub fn load_with_material(
+        path: &Path,
+        material: SharedMaterial,
+        bvh: &bvh::Settings,
+    ) -> Result<Mesh, Error> {
+        Mesh::parse(path, Some(material), bvh)
+    }
+
+    fn parse(
+        path: &Path,
+        material: Option<SharedMaterial>,
+        bvh: &bvh::Settings,
+    ) -> Result<Mesh, Error> {
+        let text = fs::read_to_string(path).map_err(|e| Error::Io(path.to_path_buf(), e))?;
+        let base = path.parent().unwrap_or(Path::new("."));
+
+        let default_material: SharedMaterial = material.clone().unwrap_or_else(|| {
+            Arc::new(Lambertian {
+                texture: SolidColor::new(Vec3::scalar(0.73)),
+            })
+        });
+        let mut library = HashMap::new();
+        let mut built: HashMap<String, SharedMaterial> = HashMap::new();
+        let mut current = default_material.clone();
+
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        let mut normals = Vec::new();
+        let mut triangles: Vec<Box<dyn Hittable>> = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| Error::Parse {
+                path: path.to_path_buf(),
+                line: index + 1,
+                message,
+            };
+
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => positions.push(parse_vec3(&mut tokens).map_err(error)?),
+                Some("vn") => normals.push(parse_vec3(&mut tokens).map_err(error)?),
+                Some("vt") => {
+                    let u = parse_float(tokens.next()).map_err(error)?;
+                    let v = tokens.next().map_or(Ok(0.0), |v| parse_float(Some(v)));
+                    uvs.push((u, v.map_err(error)?));
+                }
+                Some("f") => {
+                    let corners = tokens
+                        .map(|corner| {
+                            parse_corner(corner, positions.len(), uvs.len(), normals.len())
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(error)?;
+                    if corners.len() < 3 {
+                        return Err(error("faces need at least three vertices".to_string()));
+                    }
+
+                    for i in 1..corners.len() - 1 {
+                        let [a, b, c] = [corners[0], corners[i], corners[i + 1]];
+                        let (pa, pb, pc) = (positions[a.0], positions[b.0], positions[c.0]);
+                        if (pb - pa).cross(pc - pa).near_zero() {
+                            continue;
+                        }
+
+                        let mut triangle = Triangle::new(pa, pb, pc, current.clone());
+                        if let (Some(ta), Some(tb), Some(tc)) = (a.1, b.1, c.1) {
+                            triangle = triangle.with_uvs([uvs[ta], uvs[tb], uvs[tc]]);
+                        }
+                        if let (Some(na), Some(nb), Some(nc)) = (a.2, b.2, c.2) {
+                            triangle =
+                                triangle.with_normals([normals[na], normals[nb], normals[nc]]);
+                        }
+
+                        triangles.push(Box::new(triangle));
+                    }
+                }
+                Some("mtllib") if material.is_none() => {
+                    for file in tokens {
+                        library.extend(load_library(&base.join(file))?);
+                    }
+                }
+                Some("usemtl") if material.is_none() => {
+                    let name = tokens.next().unwrap_or_default();
+                    current = match (built.get(name), library.get(name)) {
+                        (Some(material), _) => material.clone(),
+                        (None, Some(description)) => {
+                            let material = build_material(description, base)?;
+                            built.insert(name.to_string(), material.clone());
+                            material
+                        }
+                        (None, None) => default_material.clone(),
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        if triangles.is_empty() {
+            return Err(Error::Empty(path.to_path_buf()));
+        }
+
+        Ok(Mesh {
+            triangles: Node::from_list(triangles, bvh),
+        })
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rng: &mut SmallRng) -> Option<HitRecord<'_>> {
+        self.triangles.hit(ray, ray_t, rng)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.triangles.bounding_box()
+    }
+}
+
+fn parse_float(token: Option<&str>) -> Result<f64, String> {
+    let token = token.ok_or_else(|| "missing number".to_string())?;
+    token
+        .parse()
+        .map_err(|_| format!("`{token}` is not a number"))
+}
+
+fn parse_vec3(tokens: &mut SplitWhitespace) -> Result<Vec3, String> {
+    Ok(Vec3([
+        parse_float(tokens.next())?,
+        parse_float(tokens.next())?,
+        parse_float(tokens.next())?,
+    ]))
+}
+
+/// Resolves a 1-based (or negative, relative) OBJ index against the number of entries so far.
+fn parse_index(token: &str, count: usize) -> Result<usize, String> {
+    let index: i64 = token
+        .parse()
+        .map_err(|_| format!("`{token}` is not an index"))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("index {index} is out of range"));
+    }
+
+    Ok(resolved as usize)
+}
+
+type Corner = (usize, Option<usize>, Option<usize>);
+
+fn parse_corner(
+    token: &str,
+    positions: usize,
+    uvs: usize,
+    normals: usize,
+) -> Result<Corner, String> {
+    let mut parts = token.split('/');
+    let position = parse_index(parts.next().unwrap_or_default(), positions)?;
+    let uv = match parts.next() {
+        Some(uv) if !uv.is_empty() => Some(parse_index(uv, uvs)?),
+        _ => None,
+    };
+    let normal = match parts.next() {
+        Some(normal) if !normal.is_empty() => Some(parse_index(normal, normals)?),
+        _ => None,
+    };
+
+    Ok((position, uv, normal))
+}
+
+/// The subset of an MTL material that maps onto the renderer's materials.
+#[derive(Debug, Clone)]
+struct MtlMaterial {
+    diffuse: Vec3,
+    specular: Vec3,
+    emission: Vec3,
+    shininess: f64,
+    refraction_index: f64,
+    dissolve: f64,
+    illumination: u32,
+    diffuse_map: Option<PathBuf>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        MtlMaterial {
+            diffuse: Vec3::scalar(0.8),
+            specular: Vec3::scalar(0.0),
+            emission: Vec3::scalar(0.0),
+            shininess: 0.0,
+            refraction_index: 1.5,
+            dissolve: 1.0,
+            illumination: 2,
+            diffuse_map: None,
+        }
+    }
+}
+
+fn load_library(path: &Path) -> Result<HashMap<String, MtlMaterial>, Error> {
+    let text = fs::read_to_string(path).map_err(|e| Error::Io(path.to_path_buf(), e))?;
+
+    let mut library = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: String| Error::Parse {
+            path: path.to_path_buf(),
+            line: index + 1,
+            message,
+        };
+
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next();
+        if keyword == Some("newmtl") {
+            library.extend(current.take());
+            let name = tokens.next().unwrap_or_default().to_string();
+            current = Some((name, MtlMaterial::default()));
+            continue;
+        }
+
+        let Some((_, material)) = current.as_mut() else {
+            continue;
+        };
+
+        match keyword {
+            Some("Kd") => material.diffuse = parse_vec3(&mut tokens).map_err(error)?,
+            Some("Ks") => material.specular = parse_vec3(&mut tokens).map_err(error)?,
+            Some("Ke") => material.emission = parse_vec3(&mut tokens).map_err(error)?,
+            Some("Ns") => material.shininess = parse_float(tokens.next()).map_err(error)?,
+            Some("Ni") => material.refraction_index = parse_float(tokens.next()).map_err(error)?,
+            Some("d") => material.dissolve = parse_float(tokens.next()).map_err(error)?,
+            Some("Tr") => material.dissolve = 1.0 - parse_float(tokens.next()).map_err(error)?,
+            Some("illum") => {
+                material.illumination = parse_float(tokens.next()).map_err(error)? as u32
+            }
+            // Options such as `-bm 1.0` come before the file name, which is always last.
+            Some("map_Kd") => material.diffuse_map = tokens.last().map(PathBuf::from),
+            _ => {}
+        }
+    }
+    library.extend(current);
+
+    Ok(library)
+}
+
+/// Emissive materials become lights, transparent or refracting ones glass, reflective ones metal
+/// and everything else diffuse.
+fn build_material(material: &MtlMaterial, base: &Path) -> Result<SharedMaterial, Error> {
+    if !material.emission.near_zero() {
+        return Ok(Arc::new(DiffuseLight::new(SolidColor::new(
+            material.emission,
+        ))));
+    }
+
+    if material.dissolve < 1.0 || matches!(material.illumination, 4 | 6 | 7 | 9) {
+        return Ok(Arc::new(Dielectric {
+            refraction_index: material.refraction_index,
+        }));
+    }
+
+    if matches!(material.illumination, 3 | 5 | 8) {
+        return Ok(Arc::new(Metal {
+            albedo: material.specular,
+            fuzz: (2.0 / (material.shininess + 2.0)).sqrt().min(1.0),
+        }));
+    }
+
+    let texture: Arc<dyn Texture + Send> = match &material.diffuse_map {
+        Some(map) => {
+            let path = base.join(map);
+            let image = image::open(&path).map_err(|e| Error::Image(path, e))?;
+            Arc::new(Image::new(image.into_rgb8()))
+        }
+        None => Arc::new(SolidColor::new(material.diffuse)),
+    };
+
+    Ok(Arc::new(Lambertian { texture }))
+}
@@ -0,0 +1,29 @@
+use crate::vec3::Vec3;
+
+/// An orthonormal basis built around a single direction, for sampling in local coordinates.
+#[derive(Debug, Copy, Clone)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn new(n: Vec3) -> Onb {
+        let w = n.unit();
+        let a = if w[0].abs() > 0.9 {
+            Vec3::y(1.0)
+        } else {
+            Vec3::x(1.0)
+        };
+        let v = w.cross(a).unit();
+        let u = w.cross(v);
+
+        Onb { u, v, w }
+    }
+
+    /// Maps a vector given in this basis' coordinates back to world space.
+    pub fn transform(&self, local: Vec3) -> Vec3 {
+        local[0] * self.u + local[1] * self.v + local[2] * self.w
+    }
+}
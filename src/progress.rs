@@ -0,0 +1,203 @@
+use crate::tile::Tile;
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// How far a render has come, as handed to [`Camera::on_progress`](crate::Camera::on_progress).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Progress {
+    /// Camera rays traced so far, one per sample.
+    pub rays: u64,
+    /// Camera rays still to trace. Adaptive sampling may stop short of these.
+    pub remaining: u64,
+    pub tiles_done: usize,
+    pub tiles: usize,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f64 {
+        let total = self.rays + self.remaining;
+        if total == 0 {
+            1.0
+        } else {
+            self.rays as f64 / total as f64
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// The time left if the rest of the render goes as fast as it has so far.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.rays == 0 {
+            return None;
+        }
+
+        Some(
+            self.elapsed
+                .mul_f64(self.remaining as f64 / self.rays as f64),
+        )
+    }
+
+    pub fn rays_per_second(&self) -> f64 {
+        self.rays as f64 / self.elapsed.as_secs_f64().max(1e-9)
+    }
+
+    /// The progress as a single line of JSON.
+    pub fn to_json(&self) -> String {
+        let eta = match self.eta() {
+            Some(eta) => format!("{:.3}", eta.as_secs_f64()),
+            None => "null".to_string(),
+        };
+
+        format!(
+            "{{\"fraction\":{:.6},\"rays\":{},\"remaining\":{},\"tiles_done\":{},\"tiles\":{},\
+             \"elapsed_secs\":{:.3},\"eta_secs\":{eta},\"rays_per_second\":{:.1}}}",
+            self.fraction(),
+            self.rays,
+            self.remaining,
+            self.tiles_done,
+            self.tiles,
+            self.elapsed.as_secs_f64(),
+            self.rays_per_second(),
+        )
+    }
+}
+
+/// A progress bar with the ETA and ray rate, redrawn in place on standard error at most ten
+/// times a second.
+#[derive(Debug, Default)]
+pub struct Bar {
+    drawn: Option<Instant>,
+}
+
+impl Bar {
+    const WIDTH: usize = 30;
+
+    pub fn new() -> Bar {
+        Bar::default()
+    }
+
+    pub fn update(&mut self, progress: &Progress) {
+        let due = self
+            .drawn
+            .is_none_or(|drawn| drawn.elapsed() >= Duration::from_millis(100));
+        if !(due || progress.done()) {
+            return;
+        }
+        self.drawn = Some(Instant::now());
+
+        let filled = (progress.fraction() * Bar::WIDTH as f64) as usize;
+        let eta = match progress.eta() {
+            Some(eta) => format_duration(eta),
+            None => "--".to_string(),
+        };
+
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r[{}{}] {:5.1}%  ETA {eta:>8}  {} rays/s  tiles {}/{}\x1b[K",
+            "#".repeat(filled),
+            "-".repeat(Bar::WIDTH - filled),
+            progress.fraction() * 100.0,
+            format_rate(progress.rays_per_second()),
+            progress.tiles_done,
+            progress.tiles,
+        );
+        if progress.done() {
+            let _ = writeln!(stderr);
+        }
+        let _ = stderr.flush();
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs / 60 % 60),
+    }
+}
+
+fn format_rate(rate: f64) -> String {
+    if rate >= 1e6 {
+        format!("{:.2}M", rate / 1e6)
+    } else if rate >= 1e3 {
+        format!("{:.1}k", rate / 1e3)
+    } else {
+        format!("{rate:.0}")
+    }
+}
+
+/// Keeps the counts behind [`Progress`] for a set of tiles up to date as they are sampled.
+pub(crate) struct Tracker {
+    started: Instant,
+    target: u32,
+    tiles: Vec<Tile>,
+    index: HashMap<Tile, usize>,
+    /// The samples each tile has and the samples it still needs.
+    work: Vec<(u64, u64)>,
+    rays: u64,
+    remaining: u64,
+    tiles_done: usize,
+}
+
+impl Tracker {
+    /// Starts tracking `tiles` towards `target` samples per pixel, with `work` giving the samples
+    /// a tile has and still needs.
+    pub(crate) fn new(tiles: Vec<Tile>, target: u32, work: impl Fn(&Tile) -> (u64, u64)) -> Self {
+        let index = tiles
+            .iter()
+            .enumerate()
+            .map(|(i, tile)| (*tile, i))
+            .collect();
+        let work: Vec<(u64, u64)> = tiles.iter().map(work).collect();
+
+        Tracker {
+            started: Instant::now(),
+            target,
+            remaining: work.iter().map(|(_, remaining)| remaining).sum(),
+            tiles_done: work.iter().filter(|(_, remaining)| *remaining == 0).count(),
+            tiles,
+            index,
+            work,
+            rays: 0,
+        }
+    }
+
+    pub(crate) fn target(&self) -> u32 {
+        self.target
+    }
+
+    pub(crate) fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    /// Records that `tile` now has the samples and needs the samples in `work`.
+    pub(crate) fn update(&mut self, tile: &Tile, work: (u64, u64)) {
+        let Some(&i) = self.index.get(tile) else {
+            return;
+        };
+
+        let (samples, remaining) = self.work[i];
+        self.rays += work.0.saturating_sub(samples);
+        self.remaining = self.remaining - remaining + work.1;
+        if remaining > 0 && work.1 == 0 {
+            self.tiles_done += 1;
+        }
+        self.work[i] = work;
+    }
+
+    pub(crate) fn progress(&self) -> Progress {
+        Progress {
+            rays: self.rays,
+            remaining: self.remaining,
+            tiles_done: self.tiles_done,
+            tiles: self.tiles.len(),
+            elapsed: self.started.elapsed(),
+        }
+    }
+}
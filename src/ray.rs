@@ -1,5 +1,7 @@
 use crate::hittable::Hittable;
 use crate::interval::Interval;
+use crate::sampler::SampleStream;
+use crate::stats::{self, Counter};
 use crate::vec3::Vec3;
 
 pub struct Ray {
@@ -13,19 +15,116 @@ impl Ray {
         self.origin + self.direction * t
     }
 
-    pub fn color<H: Hittable + ?Sized>(&self, depth: u32, world: &H, background: Vec3) -> Vec3 {
+    /// Estimates the radiance arriving along this ray. Besides following scattered rays, each
+    /// non-specular bounce also samples `lights` directly, and both estimates are combined with the
+    /// power heuristic so that neither small lights nor glossy reflections of them get noisy.
+    pub fn color<H, L>(
+        &self,
+        depth: u32,
+        world: &H,
+        lights: &L,
+        background: Vec3,
+        samples: &mut SampleStream,
+    ) -> Vec3
+    where
+        H: Hittable + ?Sized,
+        L: Hittable + ?Sized,
+    {
+        self.trace(depth, world, lights, background, None, samples)
+    }
+
+    /// `bsdf_pdf` is the density with which the previous bounce sampled this ray, or `None` if
+    /// it came from a delta lobe that lights could not have been sampled for.
+    fn trace<H, L>(
+        &self,
+        depth: u32,
+        world: &H,
+        lights: &L,
+        background: Vec3,
+        bsdf_pdf: Option<f64>,
+        samples: &mut SampleStream,
+    ) -> Vec3
+    where
+        H: Hittable + ?Sized,
+        L: Hittable + ?Sized,
+    {
         if depth == 0 {
             return Vec3::scalar(0.0);
         }
+        stats::count(Counter::PathRays);
 
-        if let Some(hit) = world.hit(self, Interval::new(0.001, f64::MAX)) {
-            let emission = hit.material.emitted(hit.u, hit.v, hit.point);
-            if let Some((scattered, attenuation)) = hit.material.scatter(self, &hit) {
-                return emission + attenuation * scattered.color(depth - 1, world, background);
-            }
+        let Some(hit) = world.hit(self, Interval::new(0.001, f64::MAX), samples.rng()) else {
+            return background;
+        };
+
+        let mut emission = hit.material.emitted(hit.u, hit.v, hit.point);
+        if let Some(pdf) = bsdf_pdf {
+            let light_pdf = lights.pdf_value(self);
+            emission *= power_heuristic(pdf, light_pdf);
+        }
+
+        // Every bounce draws the same dimensions, whatever the material, so they stay aligned
+        // between the samples of a pixel.
+        let u_light = samples.get_2d();
+        let u_bsdf = samples.get_2d();
+
+        let wo = -self.direction.unit();
+        let Some(sample) = hit.material.sample(&hit, wo, u_bsdf) else {
             return emission;
+        };
+
+        let scattered = Ray {
+            origin: hit.point,
+            direction: sample.direction,
+            time: self.time,
+        };
+        if sample.delta {
+            return emission
+                + sample.weight()
+                    * scattered.trace(depth - 1, world, lights, background, None, samples);
+        }
+
+        let mut direct = Vec3::scalar(0.0);
+        let to_light = Ray {
+            origin: hit.point,
+            direction: lights.random(hit.point, self.time, u_light),
+            time: self.time,
+        };
+        let light_pdf = lights.pdf_value(&to_light);
+        if light_pdf > 0.0 {
+            let wi = to_light.direction.unit();
+            let bsdf_pdf = hit.material.pdf(&hit, wo, wi);
+            if bsdf_pdf > 0.0 {
+                stats::count(Counter::ShadowRays);
+                if let Some(light) =
+                    world.hit(&to_light, Interval::new(0.001, f64::MAX), samples.rng())
+                {
+                    let radiance = light.material.emitted(light.u, light.v, light.point);
+                    direct = hit.material.eval(&hit, wo, wi) * radiance / light_pdf
+                        * power_heuristic(light_pdf, bsdf_pdf);
+                }
+            }
         }
 
-        background
+        emission
+            + direct
+            + sample.weight()
+                * scattered.trace(
+                    depth - 1,
+                    world,
+                    lights,
+                    background,
+                    Some(sample.pdf),
+                    samples,
+                )
+    }
+}
+
+fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other * other);
+    if a + b == 0.0 {
+        0.0
+    } else {
+        a / (a + b)
     }
 }
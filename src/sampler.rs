@@ -0,0 +1,473 @@
+use crate::vec3::Vec3;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::sync::OnceLock;
+
+/// Supplies the numbers a camera sample consumes, one dimension at a time. The same dimension
+/// always drives the same decision, so samplers that spread each dimension evenly over a pixel's
+/// samples reduce noise. Values lie in `[0, 1)`.
+pub trait Sampler: Sync {
+    /// Dimension `dimension` of sample `index` of the pixel at `pixel`.
+    fn get(&self, pixel: (u32, u32), index: u32, dimension: u32) -> f64;
+
+    /// Dimensions `dimension` and `dimension + 1`, which some samplers stratify jointly.
+    fn get_2d(&self, pixel: (u32, u32), index: u32, dimension: u32) -> [f64; 2] {
+        [
+            self.get(pixel, index, dimension),
+            self.get(pixel, index, dimension + 1),
+        ]
+    }
+}
+
+/// Which [`Sampler`] a camera renders with.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SamplerKind {
+    #[default]
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+impl SamplerKind {
+    pub fn build(self, samples_per_pixel: u32, seed: u64) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(Independent { seed }),
+            SamplerKind::Stratified => Box::new(Stratified {
+                samples: samples_per_pixel,
+                seed,
+            }),
+            SamplerKind::Halton => Box::new(Halton { seed }),
+            SamplerKind::Sobol => Box::new(Sobol { seed }),
+            SamplerKind::BlueNoise => Box::new(BlueNoise { seed }),
+        }
+    }
+}
+
+/// Hands out the dimensions of one camera sample in order, along with a generator for the
+/// decisions that are not worth a dimension of their own.
+pub struct SampleStream<'s> {
+    sampler: &'s dyn Sampler,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+    rng: SmallRng,
+}
+
+impl<'s> SampleStream<'s> {
+    pub fn new(
+        sampler: &'s dyn Sampler,
+        pixel: (u32, u32),
+        index: u32,
+        rng: SmallRng,
+    ) -> SampleStream<'s> {
+        SampleStream {
+            sampler,
+            pixel,
+            index,
+            dimension: 0,
+            rng,
+        }
+    }
+
+    pub fn rng(&mut self) -> &mut SmallRng {
+        &mut self.rng
+    }
+
+    pub fn get_1d(&mut self) -> f64 {
+        let value = self.sampler.get(self.pixel, self.index, self.dimension);
+        self.dimension += 1;
+        value
+    }
+
+    /// Starts at an even dimension, so pairs line up with the ones samplers stratify together.
+    pub fn get_2d(&mut self) -> [f64; 2] {
+        self.dimension += self.dimension % 2;
+        let value = self.sampler.get_2d(self.pixel, self.index, self.dimension);
+        self.dimension += 2;
+        value
+    }
+}
+
+/// Independent uniform numbers, as if every decision called a random number generator.
+#[derive(Debug, Copy, Clone)]
+pub struct Independent {
+    pub seed: u64,
+}
+
+impl Sampler for Independent {
+    fn get(&self, pixel: (u32, u32), index: u32, dimension: u32) -> f64 {
+        to_unit(hash(
+            self.seed,
+            &[pack(pixel), index as u64, dimension as u64],
+        ))
+    }
+}
+
+/// Jittered strata, one per sample of the pixel, visited in a different random order in every
+/// dimension. Pairs of dimensions are stratified over a 2D grid.
+#[derive(Debug, Copy, Clone)]
+pub struct Stratified {
+    pub samples: u32,
+    pub seed: u64,
+}
+
+impl Sampler for Stratified {
+    fn get(&self, pixel: (u32, u32), index: u32, dimension: u32) -> f64 {
+        let jitter = Independent { seed: self.seed }.get(pixel, index, dimension);
+        if index >= self.samples {
+            return jitter;
+        }
+
+        let order = hash(self.seed, &[pack(pixel), dimension as u64]) as u32;
+        let stratum = permute(index, self.samples, order);
+
+        (stratum as f64 + jitter) / self.samples as f64
+    }
+
+    fn get_2d(&self, pixel: (u32, u32), index: u32, dimension: u32) -> [f64; 2] {
+        let jitter = Independent { seed: self.seed }.get_2d(pixel, index, dimension);
+        if index >= self.samples {
+            return jitter;
+        }
+
+        // The smallest grid with a cell for every sample. Cells left over stay empty.
+        let columns = (self.samples as f64).sqrt().ceil() as u32;
+        let rows = self.samples.div_ceil(columns);
+        let order = hash(self.seed, &[pack(pixel), dimension as u64]) as u32;
+        let cell = permute(index, columns * rows, order);
+
+        [
+            ((cell % columns) as f64 + jitter[0]) / columns as f64,
+            ((cell / columns) as f64 + jitter[1]) / rows as f64,
+        ]
+    }
+}
+
+/// The Halton sequence, a radical inverse in the n-th prime base for dimension n. Every digit
+/// goes through a random permutation per pixel, which decorrelates pixels and breaks up the
+/// stripes the larger bases form. Dimensions past the table of primes fall back to independent
+/// numbers.
+#[derive(Debug, Copy, Clone)]
+pub struct Halton {
+    pub seed: u64,
+}
+
+impl Sampler for Halton {
+    fn get(&self, pixel: (u32, u32), index: u32, dimension: u32) -> f64 {
+        let Some(&base) = PRIMES.get(dimension as usize) else {
+            return Independent { seed: self.seed }.get(pixel, index, dimension);
+        };
+
+        let scramble = hash(self.seed, &[pack(pixel), dimension as u64]);
+        let inverse_base = 1.0 / base as f64;
+        let mut remaining = index;
+        let mut factor = inverse_base;
+        let mut value = 0.0;
+        let mut digit = 0;
+        while factor > f64::EPSILON {
+            let order = hash(scramble, &[digit]) as u32;
+            value += permute(remaining % base, base, order) as f64 * factor;
+            remaining /= base;
+            factor *= inverse_base;
+            digit += 1;
+        }
+
+        value.min(ONE_MINUS_EPSILON)
+    }
+}
+
+/// The first two dimensions of the Sobol sequence, a (0, 2)-sequence, Owen scrambled with
+/// Burley's hash-based nested uniform scrambling. Every pair of dimensions gets its own
+/// scrambled copy, with the sample order shuffled so the pairs are not correlated.
+#[derive(Debug, Copy, Clone)]
+pub struct Sobol {
+    pub seed: u64,
+}
+
+impl Sobol {
+    fn pair(&self, pixel: (u32, u32), index: u32, pair: u32) -> [f64; 2] {
+        let seeds = [0, 1, 2].map(|i| hash(self.seed, &[pack(pixel), pair as u64, i]) as u32);
+        let index = nested_uniform_scramble(index, seeds[0]);
+
+        [
+            nested_uniform_scramble(sobol_0(index), seeds[1]),
+            nested_uniform_scramble(sobol_1(index), seeds[2]),
+        ]
+        .map(|x| x as f64 / 4294967296.0)
+    }
+}
+
+impl Sampler for Sobol {
+    fn get(&self, pixel: (u32, u32), index: u32, dimension: u32) -> f64 {
+        self.pair(pixel, index, dimension / 2)[(dimension % 2) as usize]
+    }
+
+    fn get_2d(&self, pixel: (u32, u32), index: u32, dimension: u32) -> [f64; 2] {
+        if dimension.is_multiple_of(2) {
+            self.pair(pixel, index, dimension / 2)
+        } else {
+            [
+                self.get(pixel, index, dimension),
+                self.get(pixel, index, dimension + 1),
+            ]
+        }
+    }
+}
+
+/// Every pixel uses the same scrambled Sobol points, shifted by an offset read from a blue noise
+/// mask. Neighbouring pixels then make errors that differ as much as possible, which leaves
+/// fine, even grain at low sample counts instead of clumps.
+#[derive(Debug, Copy, Clone)]
+pub struct BlueNoise {
+    pub seed: u64,
+}
+
+impl Sampler for BlueNoise {
+    fn get(&self, pixel: (u32, u32), index: u32, dimension: u32) -> f64 {
+        let value = Sobol { seed: self.seed }.get((0, 0), index, dimension);
+
+        // Each dimension reads the tiled mask at a different offset.
+        let offset = hash(self.seed, &[dimension as u64]);
+        let size = BLUE_NOISE_SIZE as u64;
+        let x = (pixel.0 as u64 + offset % size) % size;
+        let y = (pixel.1 as u64 + (offset >> 32) % size) % size;
+        let shift = blue_noise_mask()[(y * size + x) as usize];
+
+        (value + shift).fract().min(ONE_MINUS_EPSILON)
+    }
+}
+
+/// Maps a uniform 2D sample to a direction uniformly distributed over the unit sphere.
+pub fn uniform_sphere(u: [f64; 2]) -> Vec3 {
+    let z = 1.0 - 2.0 * u[0];
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u[1];
+
+    Vec3([r * phi.cos(), r * phi.sin(), z])
+}
+
+/// Maps a uniform 2D sample to a direction about +z with density `cos(theta) / pi`.
+pub fn cosine_hemisphere(u: [f64; 2]) -> Vec3 {
+    let r = u[0].sqrt();
+    let phi = 2.0 * PI * u[1];
+
+    Vec3([r * phi.cos(), r * phi.sin(), (1.0 - u[0]).sqrt()])
+}
+
+/// Maps a uniform 2D sample to the unit disk with Shirley and Chiu's concentric mapping, which
+/// keeps strata compact.
+pub fn concentric_disk(u: [f64; 2]) -> Vec3 {
+    let (a, b) = (2.0 * u[0] - 1.0, 2.0 * u[1] - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec3::scalar(0.0);
+    }
+
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+
+    Vec3([r * theta.cos(), r * theta.sin(), 0.0])
+}
+
+/// Mixes `values` into `seed` with SplitMix64's finaliser, so neighbouring inputs give unrelated
+/// outputs.
+pub(crate) fn hash(seed: u64, values: &[u64]) -> u64 {
+    let mut hash = seed;
+    for value in values {
+        hash = (hash ^ value).wrapping_add(0x9e3779b97f4a7c15);
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+        hash ^= hash >> 31;
+    }
+
+    hash
+}
+
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+const PRIMES: [u32; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+const BLUE_NOISE_SIZE: usize = 64;
+
+fn pack(pixel: (u32, u32)) -> u64 {
+    (pixel.0 as u64) << 32 | pixel.1 as u64
+}
+
+fn to_unit(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Kensler's hash-based permutation of `0..len`, from "Correlated Multi-Jittered Sampling".
+fn permute(mut i: u32, len: u32, p: u32) -> u32 {
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < len {
+            break;
+        }
+    }
+
+    (i.wrapping_add(p)) % len
+}
+
+fn sobol_0(index: u32) -> u32 {
+    index.reverse_bits()
+}
+
+/// The second Sobol dimension, whose direction numbers follow from the polynomial `x + 1`.
+fn sobol_1(mut index: u32) -> u32 {
+    let mut direction = 1 << 31;
+    let mut x = 0;
+    while index != 0 {
+        if index & 1 != 0 {
+            x ^= direction;
+        }
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+
+    x
+}
+
+/// Burley's "Practical Hash-based Owen Scrambling": a hash that only lets bits affect higher
+/// ones, applied to the reversed bits so each digit is permuted depending on the ones before it.
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits().wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+
+    x.reverse_bits()
+}
+
+/// A tileable blue noise mask made with Ulichney's void-and-cluster method: the pixels are
+/// ranked so that every threshold of the mask is an evenly spread point set. It is generated
+/// once, on first use.
+fn blue_noise_mask() -> &'static [f64] {
+    static MASK: OnceLock<Vec<f64>> = OnceLock::new();
+
+    MASK.get_or_init(|| {
+        const SIZE: usize = BLUE_NOISE_SIZE;
+        const RADIUS: isize = 8;
+        const SIGMA: f64 = 1.5;
+        let n = SIZE * SIZE;
+
+        let mut kernel = Vec::new();
+        for dy in -RADIUS..=RADIUS {
+            for dx in -RADIUS..=RADIUS {
+                let weight = (-((dx * dx + dy * dy) as f64) / (2.0 * SIGMA * SIGMA)).exp();
+                kernel.push((dx, dy, weight));
+            }
+        }
+
+        let splat = |energy: &mut [f64], i: usize, sign: f64| {
+            let (x, y) = ((i % SIZE) as isize, (i / SIZE) as isize);
+            for &(dx, dy, weight) in &kernel {
+                let jx = (x + dx).rem_euclid(SIZE as isize) as usize;
+                let jy = (y + dy).rem_euclid(SIZE as isize) as usize;
+                energy[jy * SIZE + jx] += sign * weight;
+            }
+        };
+        let tightest_cluster = |pattern: &[bool], energy: &[f64]| {
+            (0..n)
+                .filter(|&i| pattern[i])
+                .max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+                .unwrap()
+        };
+        let largest_void = |pattern: &[bool], energy: &[f64]| {
+            (0..n)
+                .filter(|&i| !pattern[i])
+                .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+                .unwrap()
+        };
+
+        // Start from a tenth of the pixels at random and relax them by moving the tightest
+        // cluster into the largest void until that stops changing anything.
+        let ones = n / 10;
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut pattern = vec![false; n];
+        let mut energy = vec![0.0; n];
+        let mut count = 0;
+        while count < ones {
+            let i = rng.gen_range(0..n);
+            if !pattern[i] {
+                pattern[i] = true;
+                splat(&mut energy, i, 1.0);
+                count += 1;
+            }
+        }
+
+        for _ in 0..n {
+            let cluster = tightest_cluster(&pattern, &energy);
+            pattern[cluster] = false;
+            splat(&mut energy, cluster, -1.0);
+
+            let void = largest_void(&pattern, &energy);
+            pattern[void] = true;
+            splat(&mut energy, void, 1.0);
+
+            if void == cluster {
+                break;
+            }
+        }
+
+        let mut rank = vec![0; n];
+
+        let (mut removing, mut removed_energy) = (pattern.clone(), energy.clone());
+        for r in (0..ones).rev() {
+            let cluster = tightest_cluster(&removing, &removed_energy);
+            removing[cluster] = false;
+            splat(&mut removed_energy, cluster, -1.0);
+            rank[cluster] = r;
+        }
+
+        // Past half full, the method ranks the tightest cluster of unset pixels instead. Every
+        // pixel's energy from all pixels is the same constant, so that is the largest void too.
+        for r in ones..n {
+            let void = largest_void(&pattern, &energy);
+            pattern[void] = true;
+            splat(&mut energy, void, 1.0);
+            rank[void] = r;
+        }
+
+        rank.into_iter()
+            .map(|r| (r as f64 + 0.5) / n as f64)
+            .collect()
+    })
+}
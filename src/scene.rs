@@ -0,0 +1,534 @@
+use crate::bvh::{self, Instance, Node};
+use crate::camera::{self, Camera, CameraBuilder};
+use crate::hittable::{
+    make_box, AnimatedTransform, ConstantMedium, Hittable, Keyframe, Quad, Sphere, Transform,
+};
+use crate::material::{Dielectric, DiffuseLight, Isotropic, Lambertian, Material, Metal};
+use crate::matrix::{Matrix4, Quaternion};
+use crate::mesh::{self, Mesh};
+use crate::texture::{Checker, Image, Noise, SolidColor, Texture};
+use crate::vec3::Vec3;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::{fs, io};
+
+type SharedMaterial = Arc<dyn Material + Send>;
+
+/// The emitters a scene's integrator samples directly.
+pub type Lights = Vec<Box<dyn Hittable>>;
+type SharedTexture = Arc<dyn Texture + Send>;
+
+/// A complete scene: the camera, the named materials and the objects that use them.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    #[serde(default)]
+    pub camera: CameraBuilder,
+    /// How the world's BVHs are built. Left out of [`Scene::content_hash`], since it never
+    /// changes the image.
+    #[serde(default)]
+    pub bvh: bvh::Settings,
+    #[serde(default)]
+    pub materials: BTreeMap<String, MaterialDescription>,
+    /// Named groups of objects built into a BVH once and placed any number of times by
+    /// `instance` objects. Prototypes cannot place instances themselves.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prototypes: BTreeMap<String, Vec<ObjectDescription>>,
+    pub objects: Vec<ObjectDescription>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TextureDescription {
+    Solid {
+        color: Vec3,
+    },
+    Checker {
+        scale: f64,
+        even: Box<TextureDescription>,
+        odd: Box<TextureDescription>,
+    },
+    /// Image paths are resolved relative to the scene file.
+    Image {
+        path: PathBuf,
+    },
+    /// Perlin noise, seeded from `seed` if given and otherwise from the name of the material it
+    /// belongs to, so editing the rest of the scene leaves it as it was.
+    Noise {
+        scale: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seed: Option<u64>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum MaterialDescription {
+    Lambertian { texture: TextureDescription },
+    Metal { albedo: Vec3, fuzz: f64 },
+    Dielectric { refraction_index: f64 },
+    DiffuseLight { texture: TextureDescription },
+    Isotropic { texture: TextureDescription },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ObjectDescription {
+    Sphere {
+        center: Vec3,
+        radius: f64,
+        material: String,
+    },
+    MovingSphere {
+        start: Vec3,
+        end: Vec3,
+        radius: f64,
+        material: String,
+    },
+    Quad {
+        q: Vec3,
+        u: Vec3,
+        v: Vec3,
+        material: String,
+    },
+    Box {
+        a: Vec3,
+        b: Vec3,
+        material: String,
+    },
+    Translate {
+        offset: Vec3,
+        object: Box<ObjectDescription>,
+    },
+    RotateY {
+        angle: f64,
+        object: Box<ObjectDescription>,
+    },
+    /// An object placed by an affine transform, given as four rows of a matrix acting on column
+    /// vectors.
+    Transform {
+        matrix: Matrix4,
+        object: Box<ObjectDescription>,
+    },
+    /// An object moved to `from` and turned so its z axis points towards `at`, with its y axis
+    /// as close to `up` as it can be.
+    LookAt {
+        from: Vec3,
+        at: Vec3,
+        up: Vec3,
+        object: Box<ObjectDescription>,
+    },
+    /// An object moving through keyframed poses while the shutter is open, blurring in the
+    /// render. Rotations more than half a turn apart take the shorter way round.
+    AnimatedTransform {
+        keyframes: Vec<KeyframeDescription>,
+        object: Box<ObjectDescription>,
+    },
+    ConstantMedium {
+        density: f64,
+        material: String,
+        boundary: Box<ObjectDescription>,
+    },
+    /// A Wavefront OBJ file, resolved relative to the scene file. Without a `material` the faces
+    /// use the materials from the OBJ's material libraries.
+    Mesh {
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+    },
+    /// A set of objects collected into their own BVH node.
+    Group {
+        objects: Vec<ObjectDescription>,
+    },
+    /// A copy of a prototype placed by an affine transform, given as four rows of a matrix acting
+    /// on column vectors.
+    Instance {
+        prototype: String,
+        transform: Matrix4,
+    },
+}
+
+/// The pose of an animated object at a time within the shutter interval, which runs from 0 to 1:
+/// scaled, then rotated by `angle` degrees about `axis`, then moved by `offset`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyframeDescription {
+    pub time: f64,
+    pub offset: Vec3,
+    pub axis: Vec3,
+    pub angle: f64,
+    pub scale: Vec3,
+}
+
+impl Default for KeyframeDescription {
+    fn default() -> Self {
+        KeyframeDescription {
+            time: 0.0,
+            offset: Vec3::scalar(0.0),
+            axis: Vec3::y(1.0),
+            angle: 0.0,
+            scale: Vec3::scalar(1.0),
+        }
+    }
+}
+
+impl KeyframeDescription {
+    fn build(&self) -> Keyframe {
+        Keyframe {
+            time: self.time,
+            offset: self.offset,
+            rotation: Quaternion::from_axis_angle(self.axis, self.angle),
+            scale: self.scale,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    Image(PathBuf, image::ImageError),
+    UnknownMaterial(String),
+    UnknownPrototype(String),
+    InvalidTransform(Box<Matrix4>),
+    InvalidKeyframes,
+    EmptyGroup,
+    Camera(camera::Error),
+    Mesh(mesh::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            Error::Parse(e) => write!(f, "invalid scene: {e}"),
+            Error::Serialize(e) => write!(f, "cannot serialize scene: {e}"),
+            Error::Image(path, e) => write!(f, "{}: {e}", path.display()),
+            Error::UnknownMaterial(name) => write!(f, "unknown material `{name}`"),
+            Error::UnknownPrototype(name) => write!(f, "unknown prototype `{name}`"),
+            Error::InvalidTransform(matrix) => write!(
+                f,
+                "transform {:?} is not an invertible affine matrix",
+                matrix.0
+            ),
+            Error::InvalidKeyframes => write!(
+                f,
+                "keyframes need finite values, non-zero axes, increasing times and scales that \
+                 are never zero or change sign"
+            ),
+            Error::EmptyGroup => {
+                write!(f, "scenes, groups and prototypes need at least one object")
+            }
+            Error::Camera(e) => write!(f, "invalid camera: {e}"),
+            Error::Mesh(e) => write!(f, "invalid mesh: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Scene {
+    pub fn load(path: &Path) -> Result<Scene, Error> {
+        let text = fs::read_to_string(path).map_err(|e| Error::Io(path.to_path_buf(), e))?;
+
+        Scene::from_toml(&text)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Scene, Error> {
+        toml::from_str(text).map_err(Error::Parse)
+    }
+
+    pub fn to_toml(&self) -> Result<String, Error> {
+        toml::to_string(self).map_err(Error::Serialize)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, self.to_toml()?).map_err(|e| Error::Io(path.to_path_buf(), e))
+    }
+
+    /// A hash of everything but the camera that stays stable between runs and platforms, so a
+    /// saved render can check it is being resumed against the same scene. Referenced image and
+    /// mesh files are identified by path only.
+    pub fn content_hash(&self) -> u64 {
+        #[derive(Serialize)]
+        struct Content<'a> {
+            materials: &'a BTreeMap<String, MaterialDescription>,
+            #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+            prototypes: &'a BTreeMap<String, Vec<ObjectDescription>>,
+            objects: &'a [ObjectDescription],
+        }
+
+        let text = toml::to_string(&Content {
+            materials: &self.materials,
+            prototypes: &self.prototypes,
+            objects: &self.objects,
+        })
+        .unwrap_or_default();
+
+        fnv1a(text.as_bytes())
+    }
+
+    /// Builds the world, the lights to sample directly and the camera, resolving relative image
+    /// paths against `base`. Noise textures are seeded from their material's name unless they
+    /// give a seed, so they look the same in every run whatever the render seed.
+    pub fn build(&self, base: &Path) -> Result<(Node, Lights, Camera), Error> {
+        let mut context = Context {
+            materials: BTreeMap::new(),
+            prototypes: BTreeMap::new(),
+            base,
+            bvh: &self.bvh,
+        };
+        for (name, description) in &self.materials {
+            let mut rng = SmallRng::seed_from_u64(fnv1a(name.as_bytes()));
+            let material = description.build(base, &mut rng)?;
+            context.materials.insert(name.as_str(), material);
+        }
+
+        let mut prototypes = BTreeMap::new();
+        for (name, objects) in &self.prototypes {
+            prototypes.insert(name.as_str(), Arc::new(build_group(objects, &context)?));
+        }
+        context.prototypes = prototypes;
+
+        let world = build_group(&self.objects, &context)?;
+
+        let mut lights = Vec::new();
+        for object in &self.objects {
+            object.collect_lights(&self.materials, &context, &mut lights)?;
+        }
+
+        let camera = self.camera.build().map_err(Error::Camera)?;
+
+        Ok((world, lights, camera))
+    }
+}
+
+impl TextureDescription {
+    fn build(&self, base: &Path, rng: &mut SmallRng) -> Result<SharedTexture, Error> {
+        Ok(match self {
+            TextureDescription::Solid { color } => Arc::new(SolidColor::new(*color)),
+            TextureDescription::Checker { scale, even, odd } => Arc::new(Checker::new(
+                *scale,
+                even.build(base, rng)?,
+                odd.build(base, rng)?,
+            )),
+            TextureDescription::Image { path } => {
+                let path = base.join(path);
+                let image = image::open(&path).map_err(|e| Error::Image(path, e))?;
+                Arc::new(Image::new(image.into_rgb8()))
+            }
+            TextureDescription::Noise { scale, seed } => match seed {
+                Some(seed) => Arc::new(Noise::<256>::new(
+                    *scale,
+                    &mut SmallRng::seed_from_u64(*seed),
+                )),
+                None => Arc::new(Noise::<256>::new(*scale, rng)),
+            },
+        })
+    }
+}
+
+impl MaterialDescription {
+    fn build(&self, base: &Path, rng: &mut SmallRng) -> Result<SharedMaterial, Error> {
+        Ok(match self {
+            MaterialDescription::Lambertian { texture } => Arc::new(Lambertian {
+                texture: texture.build(base, rng)?,
+            }),
+            MaterialDescription::Metal { albedo, fuzz } => Arc::new(Metal {
+                albedo: *albedo,
+                fuzz: *fuzz,
+            }),
+            MaterialDescription::Dielectric { refraction_index } => Arc::new(Dielectric {
+                refraction_index: *refraction_index,
+            }),
+            MaterialDescription::DiffuseLight { texture } => {
+                Arc::new(DiffuseLight::new(texture.build(base, rng)?))
+            }
+            MaterialDescription::Isotropic { texture } => {
+                Arc::new(Isotropic::new(texture.build(base, rng)?))
+            }
+        })
+    }
+}
+
+/// What objects are built from: the scene's materials and prototypes, the directory relative
+/// paths start from and how BVHs are built.
+struct Context<'a> {
+    materials: BTreeMap<&'a str, SharedMaterial>,
+    prototypes: BTreeMap<&'a str, Arc<Node>>,
+    base: &'a Path,
+    bvh: &'a bvh::Settings,
+}
+
+impl ObjectDescription {
+    fn build(&self, context: &Context) -> Result<Box<dyn Hittable>, Error> {
+        let material = |name: &String| {
+            context
+                .materials
+                .get(name.as_str())
+                .cloned()
+                .ok_or_else(|| Error::UnknownMaterial(name.clone()))
+        };
+
+        Ok(match self {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material: name,
+            } => Box::new(Sphere::new(*center, *radius, material(name)?)),
+            ObjectDescription::MovingSphere {
+                start,
+                end,
+                radius,
+                material: name,
+            } => Box::new(Sphere::moving(*start, *end, *radius, material(name)?)),
+            ObjectDescription::Quad {
+                q,
+                u,
+                v,
+                material: name,
+            } => Box::new(Quad::new(*q, *u, *v, material(name)?)),
+            ObjectDescription::Box {
+                a,
+                b,
+                material: name,
+            } => Box::new(make_box(*a, *b, material(name)?)),
+            ObjectDescription::Translate { .. }
+            | ObjectDescription::RotateY { .. }
+            | ObjectDescription::Transform { .. }
+            | ObjectDescription::LookAt { .. } => {
+                let (mut matrix, mut object) = self.placement().unwrap();
+                while let Some((inner, next)) = object.placement() {
+                    matrix = matrix * inner;
+                    object = next;
+                }
+
+                Box::new(
+                    Transform::new(object.build(context)?, matrix)
+                        .ok_or_else(|| Error::InvalidTransform(Box::new(matrix)))?,
+                )
+            }
+            ObjectDescription::AnimatedTransform { keyframes, object } => Box::new(
+                AnimatedTransform::new(
+                    object.build(context)?,
+                    keyframes.iter().map(KeyframeDescription::build).collect(),
+                )
+                .ok_or(Error::InvalidKeyframes)?,
+            ),
+            ObjectDescription::ConstantMedium {
+                density,
+                material: name,
+                boundary,
+            } => Box::new(ConstantMedium::new(
+                boundary.build(context)?,
+                *density,
+                material(name)?,
+            )),
+            ObjectDescription::Mesh {
+                path,
+                material: name,
+            } => {
+                let path = context.base.join(path);
+                let mesh = match name {
+                    Some(name) => Mesh::load_with_material(&path, material(name)?, context.bvh),
+                    None => Mesh::load(&path, context.bvh),
+                };
+                Box::new(mesh.map_err(Error::Mesh)?)
+            }
+            ObjectDescription::Group { objects } => Box::new(build_group(objects, context)?),
+            ObjectDescription::Instance {
+                prototype,
+                transform,
+            } => {
+                let object = context
+                    .prototypes
+                    .get(prototype.as_str())
+                    .ok_or_else(|| Error::UnknownPrototype(prototype.clone()))?;
+                Box::new(
+                    Instance::new(object.clone(), *transform)
+                        .ok_or_else(|| Error::InvalidTransform(Box::new(*transform)))?,
+                )
+            }
+        })
+    }
+}
+
+impl ObjectDescription {
+    /// The matrix and object of descriptions that place another object, so directly nested
+    /// placements can be built as one transform.
+    fn placement(&self) -> Option<(Matrix4, &ObjectDescription)> {
+        match self {
+            ObjectDescription::Translate { offset, object } => {
+                Some((Matrix4::translation(*offset), object))
+            }
+            ObjectDescription::RotateY { angle, object } => {
+                Some((Matrix4::rotation(Vec3::y(1.0), *angle), object))
+            }
+            ObjectDescription::Transform { matrix, object } => Some((*matrix, object)),
+            ObjectDescription::LookAt {
+                from,
+                at,
+                up,
+                object,
+            } => Some((Matrix4::look_at(*from, *at, *up), object)),
+            _ => None,
+        }
+    }
+
+    /// Adds a copy of every sphere, moving sphere and quad with a `diffuse-light` material to
+    /// `lights`. Emitters inside transforms, media, meshes or instances are left out and only
+    /// found by scattered rays.
+    fn collect_lights(
+        &self,
+        descriptions: &BTreeMap<String, MaterialDescription>,
+        context: &Context,
+        lights: &mut Lights,
+    ) -> Result<(), Error> {
+        match self {
+            ObjectDescription::Sphere { material, .. }
+            | ObjectDescription::MovingSphere { material, .. }
+            | ObjectDescription::Quad { material, .. }
+                if matches!(
+                    descriptions.get(material),
+                    Some(MaterialDescription::DiffuseLight { .. })
+                ) =>
+            {
+                lights.push(self.build(context)?);
+            }
+            ObjectDescription::Group { objects } => {
+                for object in objects {
+                    object.collect_lights(descriptions, context, lights)?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// The 64-bit FNV-1a hash, which unlike the standard library's hashers is the same on every run
+/// and platform.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn build_group(objects: &[ObjectDescription], context: &Context) -> Result<Node, Error> {
+    if objects.is_empty() {
+        return Err(Error::EmptyGroup);
+    }
+
+    let objects = objects
+        .iter()
+        .map(|object| object.build(context))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Node::from_list(objects, context.bvh))
+}
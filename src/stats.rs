@@ -0,0 +1,237 @@
+//! Counters for where a render spends its time. Threads count into their own counters, which
+//! are added to the process-wide totals every tile, so counting stays cheap in the hot loops.
+
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum Counter {
+    CameraRays,
+    /// Every ray a path follows, camera rays included.
+    PathRays,
+    ShadowRays,
+    AabbTests,
+    SphereTests,
+    QuadTests,
+    TriangleTests,
+    MediumTests,
+    BvhTraversals,
+    BvhNodes,
+    /// The sum of the depths of every BVH node visited.
+    BvhDepth,
+    BuildNanos,
+    RenderNanos,
+}
+
+const COUNTERS: usize = Counter::RenderNanos as usize + 1;
+
+static TOTALS: [AtomicU64; COUNTERS] = [const { AtomicU64::new(0) }; COUNTERS];
+
+/// This thread's counts since it last flushed them, and how deep into nested BVH nodes it is.
+struct Local {
+    counts: [Cell<u64>; COUNTERS],
+    bvh_depth: Cell<u64>,
+}
+
+impl Local {
+    #[inline]
+    fn add(&self, counter: Counter, n: u64) {
+        let count = &self.counts[counter as usize];
+        count.set(count.get() + n);
+    }
+}
+
+thread_local! {
+    static LOCAL: Local = const {
+        Local {
+            counts: [const { Cell::new(0) }; COUNTERS],
+            bvh_depth: Cell::new(0),
+        }
+    };
+}
+
+#[inline]
+pub(crate) fn count(counter: Counter) {
+    add(counter, 1);
+}
+
+#[inline]
+pub(crate) fn add(counter: Counter, n: u64) {
+    LOCAL.with(|local| local.add(counter, n));
+}
+
+/// Adds the time since `start` to `counter`.
+pub(crate) fn time(counter: Counter, start: Instant) {
+    add(counter, start.elapsed().as_nanos() as u64);
+}
+
+/// Moves this thread's counts into the process-wide totals.
+pub(crate) fn flush() {
+    LOCAL.with(|local| {
+        for (count, total) in local.counts.iter().zip(&TOTALS) {
+            total.fetch_add(count.take(), Ordering::Relaxed);
+        }
+    });
+}
+
+/// A ray's traversal of one BVH, which counts the nodes it visits at their depth below any BVHs
+/// it is nested in.
+pub(crate) struct BvhVisit {
+    depth: u64,
+}
+
+impl BvhVisit {
+    #[inline]
+    pub(crate) fn enter() -> BvhVisit {
+        LOCAL.with(|local| {
+            let depth = local.bvh_depth.get();
+            local.add(Counter::BvhTraversals, (depth == 0) as u64);
+
+            BvhVisit { depth }
+        })
+    }
+
+    /// Counts a visit to a node `depth` levels below this BVH's root, and places any BVH hit
+    /// from the node's primitives below it.
+    #[inline]
+    pub(crate) fn node(&self, depth: u64) {
+        LOCAL.with(|local| {
+            let depth = self.depth + depth;
+            local.add(Counter::BvhNodes, 1);
+            local.add(Counter::BvhDepth, depth);
+            local.bvh_depth.set(depth + 1);
+        });
+    }
+}
+
+impl Drop for BvhVisit {
+    #[inline]
+    fn drop(&mut self) {
+        LOCAL.with(|local| local.bvh_depth.set(self.depth));
+    }
+}
+
+/// What this process has traced and built so far. Distributed renders only count the work done
+/// in each process.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub camera_rays: u64,
+    /// Rays scattered from surfaces and media.
+    pub secondary_rays: u64,
+    /// Rays towards sampled lights.
+    pub shadow_rays: u64,
+    pub aabb_tests: u64,
+    pub sphere_tests: u64,
+    pub quad_tests: u64,
+    pub triangle_tests: u64,
+    pub medium_tests: u64,
+    /// Rays tested against a BVH from its root.
+    pub bvh_traversals: u64,
+    pub bvh_nodes_visited: u64,
+    pub bvh_depth_sum: u64,
+    pub bvh_build: Duration,
+    pub render: Duration,
+}
+
+impl Stats {
+    /// The totals of every thread that has finished a tile, and of this one.
+    pub fn collect() -> Stats {
+        flush();
+        let total = |counter: Counter| TOTALS[counter as usize].load(Ordering::Relaxed);
+
+        Stats {
+            camera_rays: total(Counter::CameraRays),
+            secondary_rays: total(Counter::PathRays).saturating_sub(total(Counter::CameraRays)),
+            shadow_rays: total(Counter::ShadowRays),
+            aabb_tests: total(Counter::AabbTests),
+            sphere_tests: total(Counter::SphereTests),
+            quad_tests: total(Counter::QuadTests),
+            triangle_tests: total(Counter::TriangleTests),
+            medium_tests: total(Counter::MediumTests),
+            bvh_traversals: total(Counter::BvhTraversals),
+            bvh_nodes_visited: total(Counter::BvhNodes),
+            bvh_depth_sum: total(Counter::BvhDepth),
+            bvh_build: Duration::from_nanos(total(Counter::BuildNanos)),
+            render: Duration::from_nanos(total(Counter::RenderNanos)),
+        }
+    }
+
+    pub fn rays(&self) -> u64 {
+        self.camera_rays + self.secondary_rays + self.shadow_rays
+    }
+
+    pub fn primitive_tests(&self) -> u64 {
+        self.sphere_tests + self.quad_tests + self.triangle_tests + self.medium_tests
+    }
+
+    /// How deep the BVH nodes visited were on average, counting the root as depth zero.
+    pub fn average_bvh_depth(&self) -> f64 {
+        self.bvh_depth_sum as f64 / self.bvh_nodes_visited.max(1) as f64
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"camera_rays\":{},\"secondary_rays\":{},\"shadow_rays\":{},\"aabb_tests\":{},\
+             \"sphere_tests\":{},\"quad_tests\":{},\"triangle_tests\":{},\"medium_tests\":{},\
+             \"bvh_traversals\":{},\"bvh_nodes_visited\":{},\"average_bvh_depth\":{:.3},\
+             \"bvh_build_secs\":{:.6},\"render_secs\":{:.6}}}",
+            self.camera_rays,
+            self.secondary_rays,
+            self.shadow_rays,
+            self.aabb_tests,
+            self.sphere_tests,
+            self.quad_tests,
+            self.triangle_tests,
+            self.medium_tests,
+            self.bvh_traversals,
+            self.bvh_nodes_visited,
+            self.average_bvh_depth(),
+            self.bvh_build.as_secs_f64(),
+            self.render.as_secs_f64(),
+        )
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let per_ray = |count: u64| count as f64 / self.rays().max(1) as f64;
+        let rate = self.rays() as f64 / self.render.as_secs_f64().max(1e-9);
+
+        writeln!(f, "rays                {:>16}", self.rays())?;
+        writeln!(f, "  camera            {:>16}", self.camera_rays)?;
+        writeln!(f, "  secondary         {:>16}", self.secondary_rays)?;
+        writeln!(f, "  shadow            {:>16}", self.shadow_rays)?;
+        writeln!(
+            f,
+            "aabb tests          {:>16}  {:8.2} per ray",
+            self.aabb_tests,
+            per_ray(self.aabb_tests)
+        )?;
+        writeln!(
+            f,
+            "primitive tests     {:>16}  {:8.2} per ray",
+            self.primitive_tests(),
+            per_ray(self.primitive_tests())
+        )?;
+        writeln!(f, "  spheres           {:>16}", self.sphere_tests)?;
+        writeln!(f, "  quads             {:>16}", self.quad_tests)?;
+        writeln!(f, "  triangles         {:>16}", self.triangle_tests)?;
+        writeln!(f, "  media             {:>16}", self.medium_tests)?;
+        writeln!(f, "bvh traversals      {:>16}", self.bvh_traversals)?;
+        writeln!(f, "bvh nodes visited   {:>16}", self.bvh_nodes_visited)?;
+        writeln!(f, "average bvh depth   {:>16.2}", self.average_bvh_depth())?;
+        writeln!(
+            f,
+            "bvh build           {:>15.3}s",
+            self.bvh_build.as_secs_f64()
+        )?;
+        writeln!(
+            f,
+            "render              {:>15.3}s  {:8.0} rays/s",
+            self.render.as_secs_f64(),
+            rate
+        )
+    }
+}
@@ -1,11 +1,21 @@
 use crate::vec3::Vec3;
 use image::RgbImage;
 use rand::Rng;
+use std::sync::Arc;
 
 pub trait Texture: Sync {
     fn value(&self, u: f64, v: f64, point: Vec3) -> Vec3;
 }
 
+impl<T> Texture for Arc<T>
+where
+    T: Texture + Send + ?Sized,
+{
+    fn value(&self, u: f64, v: f64, point: Vec3) -> Vec3 {
+        self.as_ref().value(u, v, point)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SolidColor {
     color: Vec3,
@@ -101,33 +111,32 @@ pub struct Perlin<const N: usize> {
 }
 
 impl<const N: usize> Perlin<N> {
-    pub fn new() -> Perlin<N> {
+    pub fn new(rng: &mut impl Rng) -> Perlin<N> {
         let mut vecs = [Vec3::scalar(0.0); N];
         for p in vecs.iter_mut() {
-            *p = Vec3::random_within(-1.0, 1.0).unit()
+            *p = Vec3::random_within(-1.0, 1.0, rng).unit()
         }
 
         Perlin {
             randvec: vecs,
-            perm_x: Perlin::generate_perm(),
-            perm_y: Perlin::generate_perm(),
-            perm_z: Perlin::generate_perm(),
+            perm_x: Perlin::generate_perm(rng),
+            perm_y: Perlin::generate_perm(rng),
+            perm_z: Perlin::generate_perm(rng),
         }
     }
 
-    fn generate_perm() -> [i32; N] {
+    fn generate_perm(rng: &mut impl Rng) -> [i32; N] {
         let mut perm = [0; N];
         for (i, p) in perm.iter_mut().enumerate() {
             *p = i as i32;
         }
 
-        Perlin::permute(perm)
+        Perlin::permute(perm, rng)
     }
 
-    fn permute(mut perm: [i32; N]) -> [i32; N] {
-        let mut rand = rand::thread_rng();
+    fn permute(mut perm: [i32; N], rng: &mut impl Rng) -> [i32; N] {
         for i in (1..N).rev() {
-            let target = rand.gen_range(0..i);
+            let target = rng.gen_range(0..i);
             perm.swap(i, target);
         }
 
@@ -201,9 +210,9 @@ pub struct Noise<const N: usize> {
 }
 
 impl<const N: usize> Noise<N> {
-    pub fn new(scale: f64) -> Noise<N> {
+    pub fn new(scale: f64, rng: &mut impl Rng) -> Noise<N> {
         Noise {
-            noise: Perlin::new(),
+            noise: Perlin::new(rng),
             scale,
         }
     }
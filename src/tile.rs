@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// A rectangle of pixels rendered as one unit of work, from `(x0, y0)` up to but not including
+/// `(x1, y1)`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Tile {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+}
+
+impl Tile {
+    pub fn width(&self) -> u32 {
+        self.x1 - self.x0
+    }
+
+    pub fn height(&self) -> u32 {
+        self.y1 - self.y0
+    }
+
+    /// The number of pixels, which may not fit in a `u32` for tiles read from elsewhere.
+    pub fn area(&self) -> u64 {
+        u64::from(self.width()) * u64::from(self.height())
+    }
+
+    /// The tile's pixels in row-major order.
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        (self.y0..self.y1).flat_map(move |y| (self.x0..self.x1).map(move |x| (x, y)))
+    }
+}
+
+/// The order tiles are handed out in, which is the order they finish in.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TileOrder {
+    /// Row by row from the top left.
+    #[default]
+    Scanline,
+    /// Outwards from the centre, where the subject usually is.
+    Spiral,
+    /// Along a Hilbert curve, so consecutive tiles are mostly neighbours. The curve covers the
+    /// smallest power-of-two square around the grid and jumps where it leaves the grid.
+    Hilbert,
+}
+
+/// Covers `area` with tiles of `size` pixels starting from its top left corner, clipped at the
+/// right and bottom edges, listed in `order`.
+pub fn tiles(area: Tile, size: u32, order: TileOrder) -> Vec<Tile> {
+    let size = size.max(1);
+    let (columns, rows) = (area.width().div_ceil(size), area.height().div_ceil(size));
+
+    let mut grid: Vec<(u32, u32)> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .collect();
+
+    match order {
+        TileOrder::Scanline => {}
+        TileOrder::Spiral => {
+            let centre = ((columns as f64 - 1.0) / 2.0, (rows as f64 - 1.0) / 2.0);
+            let key = |&(column, row): &(u32, u32)| {
+                let (dx, dy) = (column as f64 - centre.0, row as f64 - centre.1);
+                let ring = dx.abs().max(dy.abs()).round();
+                let angle = dy.atan2(dx).rem_euclid(2.0 * PI);
+                (ring, angle)
+            };
+            grid.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        }
+        TileOrder::Hilbert => {
+            let side = columns.max(rows).next_power_of_two();
+            grid.sort_by_key(|&(column, row)| hilbert_index(side, column, row));
+        }
+    }
+
+    grid.into_iter()
+        .map(|(column, row)| Tile {
+            x0: area.x0 + column * size,
+            y0: area.y0 + row * size,
+            x1: (area.x0 + (column + 1) * size).min(area.x1),
+            y1: (area.y0 + (row + 1) * size).min(area.y1),
+        })
+        .collect()
+}
+
+/// How far along the Hilbert curve filling a `side` by `side` grid the cell `(x, y)` is.
+fn hilbert_index(side: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut index = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u32;
+        let ry = (y & s > 0) as u32;
+        index += s as u64 * s as u64 * ((3 * rx) ^ ry) as u64;
+
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+
+    index
+}
@@ -0,0 +1,164 @@
+use crate::vec3::Vec3;
+use image::Rgb;
+
+/// Compresses scene-referred linear radiance into the displayable range.
+pub trait ToneMapper: Sync {
+    /// Maps linear radiance to linear display values in `[0, 1]`.
+    fn map(&self, color: Vec3) -> Vec3;
+}
+
+/// Clips each channel to `[0, 1]`, leaving values within range untouched.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Clamp;
+
+impl ToneMapper for Clamp {
+    fn map(&self, color: Vec3) -> Vec3 {
+        Vec3(color.0.map(|c| c.clamp(0.0, 1.0)))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Reinhard;
+
+impl ToneMapper for Reinhard {
+    fn map(&self, color: Vec3) -> Vec3 {
+        Vec3(color.0.map(|c| c.max(0.0) / (1.0 + c.max(0.0))))
+    }
+}
+
+/// Reinhard on luminance, with `white` being the smallest luminance that maps to pure white.
+#[derive(Debug, Copy, Clone)]
+pub struct ExtendedReinhard {
+    pub white: f64,
+}
+
+impl ToneMapper for ExtendedReinhard {
+    fn map(&self, color: Vec3) -> Vec3 {
+        let luminance = luminance(color);
+        if luminance <= 0.0 {
+            return Vec3::scalar(0.0);
+        }
+
+        let mapped = luminance * (1.0 + luminance / self.white.powi(2)) / (1.0 + luminance);
+
+        Clamp.map(color * (mapped / luminance))
+    }
+}
+
+/// Stephen Hill's fit of the ACES reference rendering and sRGB output transforms.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Aces;
+
+impl ToneMapper for Aces {
+    fn map(&self, color: Vec3) -> Vec3 {
+        const INPUT: [[f64; 3]; 3] = [
+            [0.59719, 0.35458, 0.04823],
+            [0.07600, 0.90834, 0.01566],
+            [0.02840, 0.13383, 0.83777],
+        ];
+        const OUTPUT: [[f64; 3]; 3] = [
+            [1.60475, -0.53108, -0.07367],
+            [-0.10208, 1.10813, -0.00605],
+            [-0.00327, -0.07276, 1.07602],
+        ];
+
+        let v = transform(INPUT, color);
+        let v = Vec3(v.0.map(|v| {
+            (v * (v + 0.0245786) - 0.000090537) / (v * (0.983729 * v + 0.4329510) + 0.238081)
+        }));
+
+        Clamp.map(transform(OUTPUT, v))
+    }
+}
+
+/// Troy Sobotka's AgX, using Benjamin Wrensch's polynomial approximation of the default look.
+/// Highlights desaturate towards white instead of skewing hue the way per-channel curves do.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct AgX;
+
+impl ToneMapper for AgX {
+    fn map(&self, color: Vec3) -> Vec3 {
+        const INSET: [[f64; 3]; 3] = [
+            [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+            [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+            [0.0423756549057051, 0.0784336, 0.879142973793104],
+        ];
+        const OUTSET: [[f64; 3]; 3] = [
+            [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+            [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+            [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+        ];
+        const MIN_EV: f64 = -12.47393;
+        const MAX_EV: f64 = 4.026069;
+
+        let v = transform(INSET, color);
+        let v = Vec3(v.0.map(|v| {
+            let x = (v.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+            let x2 = x * x;
+            let x4 = x2 * x2;
+
+            15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+                - 0.00232
+        }));
+        let v = transform(OUTSET, v);
+
+        // The curve produces display-encoded values, so undo its 2.2 gamma to stay linear.
+        Vec3(v.0.map(|v| v.clamp(0.0, 1.0).powf(2.2)))
+    }
+}
+
+/// Everything between the accumulated radiance and an 8-bit pixel: exposure in stops, a tone
+/// mapping operator and the sRGB transfer function.
+pub struct DisplayTransform {
+    pub exposure: f64,
+    pub tone_mapper: Box<dyn ToneMapper>,
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        DisplayTransform::new(Clamp)
+    }
+}
+
+impl DisplayTransform {
+    pub fn new(tone_mapper: impl ToneMapper + 'static) -> DisplayTransform {
+        DisplayTransform {
+            exposure: 0.0,
+            tone_mapper: Box::new(tone_mapper),
+        }
+    }
+
+    pub fn exposure(mut self, stops: f64) -> DisplayTransform {
+        self.exposure = stops;
+        self
+    }
+
+    pub fn apply(&self, color: Vec3) -> Rgb<u8> {
+        let exposed = color * 2f64.powf(self.exposure);
+        let mapped = self.tone_mapper.map(exposed);
+
+        Rgb(mapped
+            .0
+            .map(|c| (srgb_encode(c).clamp(0.0, 1.0) * 255.0).round() as u8))
+    }
+}
+
+/// Rec. 709 luminance of a linear color.
+pub fn luminance(color: Vec3) -> f64 {
+    0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2]
+}
+
+/// The sRGB opto-electronic transfer function, from linear light to encoded values.
+pub fn srgb_encode(linear: f64) -> f64 {
+    if linear.is_nan() || linear <= 0.0 {
+        0.0
+    } else if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn transform(m: [[f64; 3]; 3], v: Vec3) -> Vec3 {
+    Vec3(m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2]))
+}
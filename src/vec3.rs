@@ -1,8 +1,8 @@
-use image::Rgb;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub};
 
-#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+#[derive(Debug, Default, Copy, Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 pub struct Vec3(pub [f64; 3]);
 
 impl Vec3 {
@@ -22,52 +22,18 @@ impl Vec3 {
         Vec3([0.0, 0.0, z])
     }
 
-    pub fn random() -> Vec3 {
-        let mut rand = rand::thread_rng();
-        Vec3([rand.gen::<f64>(), rand.gen::<f64>(), rand.gen::<f64>()])
+    pub fn random(rng: &mut impl Rng) -> Vec3 {
+        Vec3([rng.gen::<f64>(), rng.gen::<f64>(), rng.gen::<f64>()])
     }
 
-    pub fn random_within(min: f64, max: f64) -> Vec3 {
-        let mut rand = rand::thread_rng();
+    pub fn random_within(min: f64, max: f64, rng: &mut impl Rng) -> Vec3 {
         Vec3([
-            rand.gen_range(min..max),
-            rand.gen_range(min..max),
-            rand.gen_range(min..max),
+            rng.gen_range(min..max),
+            rng.gen_range(min..max),
+            rng.gen_range(min..max),
         ])
     }
 
-    fn random_within_unit_sphere() -> Vec3 {
-        loop {
-            let p = Vec3::random_within(-1.0, 1.0);
-            if p.length_squared() < 1.0 {
-                return p;
-            }
-        }
-    }
-
-    pub fn random_unit_vector() -> Vec3 {
-        Vec3::random_within_unit_sphere().unit()
-    }
-
-    pub fn random_on_hemisphere(normal: Vec3) -> Vec3 {
-        let on_sphere = Vec3::random_unit_vector();
-        if on_sphere.dot(normal) > 0.0 {
-            on_sphere
-        } else {
-            -on_sphere
-        }
-    }
-
-    pub fn random_in_unit_disk() -> Vec3 {
-        let mut rand = rand::thread_rng();
-        loop {
-            let p = Vec3([rand.gen_range(-1.0..1.0), rand.gen_range(-1.0..1.0), 0.0]);
-            if p.length_squared() < 1.0 {
-                return p;
-            }
-        }
-    }
-
     pub fn length_squared(&self) -> f64 {
         self[0].powi(2) + self[1].powi(2) + self[2].powi(2)
     }
@@ -198,21 +164,3 @@ impl DivAssign<f64> for Vec3 {
         self.0.iter_mut().for_each(|x| *x /= rhs);
     }
 }
-
-impl From<Vec3> for Rgb<u8> {
-    fn from(value: Vec3) -> Self {
-        let mut smudged = value.0.iter().map(|&x| {
-            if x > 0.0 {
-                (x.sqrt().clamp(0.0, 0.999) * 256.0) as u8
-            } else {
-                0
-            }
-        });
-
-        Rgb([
-            smudged.next().unwrap(),
-            smudged.next().unwrap(),
-            smudged.next().unwrap(),
-        ])
-    }
-}